@@ -0,0 +1,167 @@
+//! Implements `#[derive(Document)]` for [elasticlunr-rs](https://docs.rs/elasticlunr-rs). This
+//! crate should be used through the `derive` feature of elasticlunr-rs, which re-exports the
+//! macro as `elasticlunr::Document`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Path};
+
+#[proc_macro_derive(Document, attributes(elasticlunr))]
+pub fn derive_document(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    is_ref: bool,
+    skip: bool,
+    store_only: bool,
+    tokenizer: Option<Path>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = FieldAttrs::default();
+        for attr in &field.attrs {
+            if !attr.path().is_ident("elasticlunr") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("ref") {
+                    attrs.is_ref = true;
+                } else if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("store_only") {
+                    attrs.store_only = true;
+                } else if meta.path.is_ident("tokenizer") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    attrs.tokenizer = Some(path.parse()?);
+                } else {
+                    return Err(meta.error("unsupported elasticlunr attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let options = [
+            attrs.is_ref,
+            attrs.skip,
+            attrs.store_only,
+            attrs.tokenizer.is_some(),
+        ];
+        if options.iter().filter(|&&option| option).count() > 1 {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`ref`, `skip`, `store_only` and `tokenizer` can't be combined",
+            ));
+        }
+        Ok(attrs)
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input,
+                    "Document can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "Document can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut parsed = Vec::new();
+    for field in fields {
+        let attrs = FieldAttrs::parse(field)?;
+        if attrs.is_ref
+            && parsed
+                .iter()
+                .any(|(_, attrs): &(_, FieldAttrs)| attrs.is_ref)
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only one field can be the document reference",
+            ));
+        }
+        parsed.push((field.ident.as_ref().unwrap(), attrs));
+    }
+
+    // Like `IndexBuilder`, the reference defaults to `id`.
+    if !parsed.iter().any(|(_, attrs)| attrs.is_ref) {
+        let id = parsed
+            .iter_mut()
+            .find(|(ident, attrs)| *ident == "id" && !attrs.skip && !attrs.store_only)
+            .ok_or_else(|| {
+                syn::Error::new(
+                    Span::call_site(),
+                    "Document needs a field named `id` or marked `#[elasticlunr(ref)]`",
+                )
+            })?;
+        id.1 = FieldAttrs {
+            is_ref: true,
+            ..Default::default()
+        };
+    }
+
+    let mut ref_field = None;
+    let mut add_fields = Vec::new();
+    let mut field_values = Vec::new();
+    let mut stored_values = Vec::new();
+    for (ident, attrs) in parsed {
+        let name = ident.unraw().to_string();
+        let value = quote!(::elasticlunr::document::FieldValue::to_text(&self.#ident));
+
+        if attrs.is_ref {
+            ref_field = Some((name, value));
+        } else if attrs.skip {
+            continue;
+        } else if attrs.store_only {
+            stored_values.push(quote!((#name, #value)));
+        } else {
+            add_fields.push(match attrs.tokenizer {
+                Some(tokenizer) => quote! {
+                    .add_field_with_tokenizer(#name, ::std::boxed::Box::new(#tokenizer))
+                },
+                None => quote!(.add_field(#name)),
+            });
+            field_values.push(value);
+        }
+    }
+    let (ref_name, ref_value) = ref_field.unwrap();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::elasticlunr::Document for #name #ty_generics #where_clause {
+            fn add_fields(builder: ::elasticlunr::IndexBuilder) -> ::elasticlunr::IndexBuilder {
+                builder.set_ref(#ref_name) #(#add_fields)*
+            }
+
+            fn doc_ref(&self) -> ::std::string::String {
+                #ref_value
+            }
+
+            fn field_values(&self) -> ::std::vec::Vec<::std::string::String> {
+                ::std::vec![#(#field_values),*]
+            }
+
+            fn stored_values(&self) -> ::std::vec::Vec<(&'static str, ::std::string::String)> {
+                ::std::vec![#(#stored_values),*]
+            }
+        }
+    })
+}
@@ -0,0 +1,17 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+fn main() -> Result<(), elasticlunr::Error> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <index.bin> <index.json>", args[0]);
+        std::process::exit(1);
+    }
+
+    let reader = BufReader::new(File::open(&args[1])?);
+    let mut writer = BufWriter::new(File::create(&args[2])?);
+    elasticlunr::binary::binary_to_json(reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
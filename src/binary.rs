@@ -0,0 +1,459 @@
+//! A compact binary format for an [`Index`], for storing indexes which are loaded again in Rust,
+//! like build caches. elasticlunr.js can't read it, so convert it back to JSON with
+//! [`binary_to_json`] before serving it to a search client.
+//!
+//! Like the JSON format, the binary format doesn't include the tokenizers added with
+//! [`IndexBuilder::add_field_with_tokenizer`](crate::IndexBuilder::add_field_with_tokenizer) or
+//! the search options added with
+//! [`IndexBuilder::add_field_with_options`](crate::IndexBuilder::add_field_with_options).
+//!
+//! # Format
+//!
+//! ```text
+//! index     := "ELRB" version:varint settings refs keys docs doc_info fields
+//! settings  := ref_field:str lang:str pipeline:strs fields:strs flags:varint
+//! refs      := strs                                  every document reference, sorted
+//! keys      := strs                                  every field name in the document store
+//! docs      := count:varint (ref:varint count:varint (key:varint text:str)*)*
+//! doc_info  := count:varint (ref:varint count:varint (key:varint length:varint)*)*
+//! fields    := count:varint (field:str node)*
+//! node      := count:varint posting* count:varint (char:varint node)*
+//! posting   := ref_delta:varint tf positions?
+//! tf        := n:varint                              sqrt(n), or if n is 0:
+//!            | 0 f64
+//! positions := count:varint position_delta:varint*   only if positions are stored
+//! strs      := count:varint str*
+//! str       := length:varint utf8-bytes
+//! ```
+//!
+//! `varint`s are unsigned LEB128 and `f64`s are 8 bytes of little endian IEEE 754. The version is
+//! [`FORMAT_VERSION`]. Bit 0 of the `flags` is set if the document store saves documents, and
+//! bit 1 if token positions are stored. `ref` and `key` are indexes into the `refs` and `keys`
+//! tables. Each `node` of the inverted index of a field has the postings of its token, sorted by
+//! reference, and its children, sorted by character. The reference of each posting is stored as
+//! the difference from the previous posting of the node, and each position as the difference
+//! from the previous position.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use crate::document_store::DocumentStore;
+use crate::inverted_index::InvertedIndex;
+use crate::{Error, Index, IndexRepr};
+
+/// The bytes which every binary index starts with.
+pub const MAGIC: &[u8; 4] = b"ELRB";
+
+/// The version of the binary format written by [`Index::write_binary`]. Reading an index with a
+/// different version fails with [`Error::UnsupportedBinaryVersion`].
+pub const FORMAT_VERSION: u64 = 1;
+
+const SAVE_DOCS: u64 = 1;
+const STORE_POSITIONS: u64 = 1 << 1;
+
+/// Converts an index in the binary format to elasticlunr.js JSON, writing the JSON to `writer`
+/// as it is serialized.
+///
+/// # Example
+/// ```
+/// # use elasticlunr::{Index, binary};
+/// let mut index = Index::new(&["title", "body"]);
+/// index.add_doc("1", &["this is a title", "this is body text"]);
+///
+/// let mut json = Vec::new();
+/// binary::binary_to_json(&index.to_binary()[..], &mut json).unwrap();
+/// assert_eq!(String::from_utf8(json).unwrap(), index.to_json());
+/// ```
+pub fn binary_to_json<R: Read, W: Write>(reader: R, writer: W) -> Result<(), Error> {
+    Index::from_binary_reader(reader)?.write_json(writer)
+}
+
+impl Index {
+    /// Returns the index, serialized to the [binary format](crate::binary).
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["this is a title", "this is body text"]);
+    ///
+    /// let binary = index.to_binary();
+    /// assert!(binary.len() < index.to_json().len());
+    /// assert_eq!(Index::from_binary(&binary).unwrap().to_json(), index.to_json());
+    /// ```
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_binary(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Serializes the index to the [binary format](crate::binary), writing it to `writer` like
+    /// [`write_json`](#method.write_json).
+    pub fn write_binary<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut enc = Encoder { writer };
+        enc.writer.write_all(MAGIC)?;
+        enc.varint(FORMAT_VERSION)?;
+
+        enc.str(&self.ref_field)?;
+        enc.str(&self.lang.name())?;
+        enc.strs(self.pipeline.names())?;
+        enc.strs(&self.fields)?;
+        let store = &self.document_store;
+        let mut flags = 0;
+        if store.save {
+            flags |= SAVE_DOCS;
+        }
+        if self.store_positions {
+            flags |= STORE_POSITIONS;
+        }
+        enc.varint(flags)?;
+
+        let mut refs = BTreeSet::new();
+        refs.extend(store.docs.keys().map(String::as_str));
+        refs.extend(store.doc_info.keys().map(String::as_str));
+        for index in self.index.values() {
+            index.for_each_posting(|doc_ref| {
+                refs.insert(doc_ref);
+            });
+        }
+        enc.strs(&refs)?;
+        let refs: BTreeMap<&str, u64> = refs.into_iter().zip(0..).collect();
+
+        let mut keys = BTreeSet::new();
+        for doc in store.docs.values() {
+            keys.extend(doc.keys().map(String::as_str));
+        }
+        for info in store.doc_info.values() {
+            keys.extend(info.keys().map(String::as_str));
+        }
+        enc.strs(&keys)?;
+        let keys: BTreeMap<&str, u64> = keys.into_iter().zip(0..).collect();
+
+        enc.varint(store.docs.len() as u64)?;
+        for (doc_ref, doc) in &store.docs {
+            enc.varint(refs[doc_ref.as_str()])?;
+            enc.varint(doc.len() as u64)?;
+            for (key, text) in doc {
+                enc.varint(keys[key.as_str()])?;
+                enc.str(text)?;
+            }
+        }
+        enc.varint(store.doc_info.len() as u64)?;
+        for (doc_ref, info) in &store.doc_info {
+            enc.varint(refs[doc_ref.as_str()])?;
+            enc.varint(info.len() as u64)?;
+            for (key, &length) in info {
+                enc.varint(keys[key.as_str()])?;
+                enc.varint(length as u64)?;
+            }
+        }
+
+        enc.varint(self.index.len() as u64)?;
+        for (field, index) in &self.index {
+            enc.str(field)?;
+            index.encode(&mut enc, &refs, self.store_positions)?;
+        }
+        Ok(())
+    }
+
+    /// Deserializes an index from the [binary format](crate::binary).
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = bytes;
+        let index = Index::from_binary_reader(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::InvalidBinary(
+                "unexpected data after the end of the index",
+            ));
+        }
+        Ok(index)
+    }
+
+    /// Deserializes an index from a reader of the [binary format](crate::binary). The index is
+    /// read in many small pieces, so wrap readers like `File` in a `BufReader`.
+    pub fn from_binary_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut dec = Decoder { reader };
+        let mut magic = [0; 4];
+        dec.reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidBinary("not a binary index"));
+        }
+        let version = dec.varint()?;
+        if version != FORMAT_VERSION {
+            return Err(Error::UnsupportedBinaryVersion(version));
+        }
+
+        let ref_field = dec.str()?;
+        let lang = dec.str()?;
+        let pipeline = dec.strs()?;
+        let fields = dec.strs()?;
+        let flags = dec.varint()?;
+        let store_positions = flags & STORE_POSITIONS != 0;
+
+        let refs = dec.strs()?;
+        let keys = dec.strs()?;
+
+        let mut document_store = DocumentStore::new(flags & SAVE_DOCS != 0);
+        for _ in 0..dec.varint()? {
+            let doc_ref = dec.lookup(&refs)?.clone();
+            let mut doc = BTreeMap::new();
+            for _ in 0..dec.varint()? {
+                let key = dec.lookup(&keys)?.clone();
+                doc.insert(key, dec.str()?);
+            }
+            document_store.docs.insert(doc_ref, doc);
+        }
+        document_store.length = document_store.docs.len();
+        for _ in 0..dec.varint()? {
+            let doc_ref = dec.lookup(&refs)?.clone();
+            let mut info = BTreeMap::new();
+            for _ in 0..dec.varint()? {
+                let key = dec.lookup(&keys)?.clone();
+                info.insert(key, dec.usize()?);
+            }
+            document_store.doc_info.insert(doc_ref, info);
+        }
+
+        let mut index = BTreeMap::new();
+        for _ in 0..dec.varint()? {
+            let field = dec.str()?;
+            index.insert(
+                field,
+                InvertedIndex::decode(&mut dec, &refs, store_positions)?,
+            );
+        }
+
+        Index::try_from(IndexRepr {
+            fields,
+            pipeline,
+            ref_field,
+            index,
+            document_store,
+            store_positions,
+            lang,
+        })
+    }
+}
+
+/// Writes the primitive values of the binary format.
+pub(crate) struct Encoder<W> {
+    writer: W,
+}
+
+impl<W: Write> Encoder<W> {
+    pub(crate) fn varint(&mut self, mut value: u64) -> io::Result<()> {
+        let mut buf = [0; 10];
+        let mut len = 0;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        self.writer.write_all(&buf[..len])
+    }
+
+    pub(crate) fn f64(&mut self, value: f64) -> io::Result<()> {
+        self.writer.write_all(&value.to_le_bytes())
+    }
+
+    fn str(&mut self, value: &str) -> io::Result<()> {
+        self.varint(value.len() as u64)?;
+        self.writer.write_all(value.as_bytes())
+    }
+
+    fn strs<I>(&mut self, values: I) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        self.varint(values.len() as u64)?;
+        for value in values {
+            self.str(value.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the primitive values of the binary format.
+pub(crate) struct Decoder<R> {
+    reader: R,
+}
+
+impl<R: Read> Decoder<R> {
+    pub(crate) fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            let bits = u64::from(byte[0] & 0x7f);
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            value |= bits << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::InvalidBinary("integer out of range"))
+    }
+
+    pub(crate) fn usize(&mut self) -> Result<usize, Error> {
+        let value = self.varint()?;
+        usize::try_from(value).map_err(|_| Error::InvalidBinary("integer out of range"))
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64, Error> {
+        let mut bytes = [0; 8];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    /// Reads an index into `table`, and returns the entry.
+    fn lookup<'t>(&mut self, table: &'t [String]) -> Result<&'t String, Error> {
+        let index = self.usize()?;
+        table
+            .get(index)
+            .ok_or(Error::InvalidBinary("reference to a missing string"))
+    }
+
+    fn str(&mut self) -> Result<String, Error> {
+        let len = self.usize()?;
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        String::from_utf8(bytes).map_err(|_| Error::InvalidBinary("invalid UTF-8"))
+    }
+
+    fn strs(&mut self) -> Result<Vec<String>, Error> {
+        (0..self.varint()?).map(|_| self.str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexBuilder;
+
+    #[test]
+    fn varints() {
+        for &value in &[0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX] {
+            let mut enc = Encoder { writer: Vec::new() };
+            enc.varint(value).unwrap();
+            let mut dec = Decoder {
+                reader: &enc.writer[..],
+            };
+            assert_eq!(dec.varint().unwrap(), value);
+            assert!(dec.reader.is_empty());
+        }
+
+        let mut enc = Encoder { writer: Vec::new() };
+        enc.varint(300).unwrap();
+        assert_eq!(enc.writer, [0xac, 0x02]);
+
+        let too_big = [0xff; 10];
+        let mut dec = Decoder {
+            reader: &too_big[..],
+        };
+        assert!(matches!(dec.varint(), Err(Error::InvalidBinary(_))));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let text = include_str!("../tests/data/en.in.txt");
+        let mut index = IndexBuilder::new().add_fields(&["title", "body"]).build();
+        for (i, line) in text.lines().enumerate() {
+            let title = line.split(' ').take(3).collect::<Vec<_>>().join(" ");
+            index.add_doc(&i.to_string(), &[&title, line]);
+        }
+        index.remove_doc("3");
+
+        let binary = index.to_binary();
+        let json = index.to_json();
+        assert_eq!(Index::from_binary(&binary).unwrap().to_json(), json);
+        assert!(binary.len() * 3 < json.len());
+
+        let mut index = IndexBuilder::new()
+            .save_docs(false)
+            .store_positions(true)
+            .set_ref("id")
+            .add_field("body")
+            .build();
+        index.add_doc("a", &["the cat sat on the cat"]);
+        index.add_doc("b", &["a dog"]);
+        // Unusual term frequencies, and tokens without postings, are kept as they are.
+        index
+            .index
+            .get_mut("body")
+            .unwrap()
+            .add_token("b", "dog", 0.3);
+        index
+            .index
+            .get_mut("body")
+            .unwrap()
+            .remove_token("a", "sat");
+
+        let loaded = Index::from_binary(&index.to_binary()).unwrap();
+        assert_eq!(loaded.to_json(), index.to_json());
+        assert_eq!(loaded.index["body"].get_positions("a", "cat"), [1, 5]);
+    }
+
+    #[test]
+    fn binary_round_trip_unsorted_positions() {
+        let mut index = IndexBuilder::new()
+            .store_positions(true)
+            .add_field("body")
+            .build();
+        index.add_doc("a", &["cat"]);
+        index
+            .index
+            .get_mut("body")
+            .unwrap()
+            .add_token_with_positions("a", "dog", 2., vec![7, 2, 4, 2]);
+
+        let loaded = Index::from_binary(&index.to_binary()).unwrap();
+        assert_eq!(loaded.index["body"].get_positions("a", "dog"), [2, 4, 7]);
+        assert_eq!(loaded.to_json(), index.to_json());
+    }
+
+    #[test]
+    fn invalid_binary() {
+        let index = Index::new(&["title", "body"]);
+        let binary = index.to_binary();
+
+        assert!(matches!(
+            Index::from_binary(b"{\"version\":\"0.9.5\"}"),
+            Err(Error::InvalidBinary(_))
+        ));
+
+        let mut newer = binary.clone();
+        newer[4] = 2;
+        assert!(matches!(
+            Index::from_binary(&newer),
+            Err(Error::UnsupportedBinaryVersion(2))
+        ));
+
+        assert!(matches!(
+            Index::from_binary(&binary[..binary.len() - 1]),
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        let mut trailing = binary;
+        trailing.push(0);
+        assert!(matches!(
+            Index::from_binary(&trailing),
+            Err(Error::InvalidBinary(_))
+        ));
+    }
+}
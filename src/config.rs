@@ -1,12 +1,14 @@
-//! These types are not used for generating `Index`es. They are provided to help with
-//! creating compatible JSON structures for configuring the JavaScript search
-//! function.
+//! These types are not used for generating `Index`es. They configure searching, with
+//! [`Index::search`](crate::Index::search) or by serializing them to compatible JSON structures
+//! for the JavaScript search function.
 //!
 //! *Reference:*
 //! <http://elasticlunr.com/docs/configuration.js.html>
 
 use std::collections::BTreeMap;
 
+use crate::{Error, Index};
+
 /// Used to set the search configuration for a specific field.
 /// When `expand` or `bool` is `None`, elasticlunr.js will use the value from
 /// the global configuration. The `boost` field, if present,
@@ -40,6 +42,33 @@ impl Default for SearchBool {
     }
 }
 
+/// Configures fuzzy matching of query tokens, which allows search terms to contain typos.
+/// elasticlunr.js doesn't support fuzzy matching, so it ignores these options.
+///
+/// Each query token matches every token in the index within `max_edits` insertions, deletions
+/// or substitutions of it, and the score of each match is reduced by `penalty` percent for
+/// every edit. Like expanded tokens, fuzzy matches don't count towards coordination.
+///
+/// |Key        |Default|
+/// |-----------|-------|
+/// |`maxEdits` |`1`    |
+/// |`penalty`  |`50`   |
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FuzzyOptions {
+    pub max_edits: u8,
+    pub penalty: u8,
+}
+
+impl Default for FuzzyOptions {
+    fn default() -> Self {
+        FuzzyOptions {
+            max_edits: 1,
+            penalty: 50,
+        }
+    }
+}
+
 /// The search configuration map which is passed to the
 /// elasticlunr.js `Index.search()` function.
 ///
@@ -47,11 +76,52 @@ impl Default for SearchBool {
 /// |--------|-------|
 /// |`bool`  |`OR`   |
 /// |`expand`|`false`|
+/// |`fields`|`None` |
+/// |`fuzzy` |`None` |
+///
+/// Like elasticlunr.js, every field is searched with a boost of 1 when `fields` is `None`, while
+/// `Some` of an empty map searches no fields.
 #[derive(Serialize, Deserialize, Default, Debug, Clone, Eq, PartialEq)]
 pub struct SearchOptions {
     pub bool: SearchBool,
     pub expand: bool,
-    pub fields: BTreeMap<String, SearchOptionsField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<BTreeMap<String, SearchOptionsField>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy: Option<FuzzyOptions>,
+}
+
+impl SearchOptions {
+    /// Checks that every field in these options is a field of `index`. elasticlunr.js ignores
+    /// unknown fields, which usually means the options were written for a different index.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::{Error, Index, config::SearchOptions};
+    /// let index = Index::new(&["title", "body"]);
+    /// let mut options = index.search_options();
+    /// assert!(options.validate(&index).is_ok());
+    ///
+    /// options
+    ///     .fields
+    ///     .get_or_insert_with(Default::default)
+    ///     .insert("tags".into(), Default::default());
+    /// assert!(matches!(
+    ///     options.validate(&index),
+    ///     Err(Error::UnknownField(field)) if field == "tags"
+    /// ));
+    /// ```
+    pub fn validate(&self, index: &Index) -> Result<(), Error> {
+        match self
+            .fields
+            .iter()
+            .flat_map(|fields| fields.keys())
+            .find(|field| !index.get_fields().contains(field))
+        {
+            Some(field) => Err(Error::UnknownField(field.clone())),
+            None => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -62,7 +132,7 @@ mod tests {
     #[test]
     fn test_normal_config() {
         let options = SearchOptions {
-            fields: btreemap![
+            fields: Some(btreemap![
                 "title".into() => SearchOptionsField {
                     boost: Some(5),
                     ..Default::default()
@@ -71,7 +141,7 @@ mod tests {
                     boost: Some(1),
                     ..Default::default()
                 },
-            ],
+            ]),
             ..Default::default()
         };
         let stringed = serde_json::to_string(&options).unwrap();
@@ -82,10 +152,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_config() {
+        let index = Index::new(&["title", "body"]);
+        let mut options = SearchOptions::default();
+        assert!(options.validate(&index).is_ok());
+
+        let fields = options.fields.get_or_insert_with(BTreeMap::new);
+        fields.insert("title".into(), SearchOptionsField::default());
+        assert!(options.validate(&index).is_ok());
+
+        let fields = options.fields.get_or_insert_with(BTreeMap::new);
+        fields.insert("breadcrumbs".into(), SearchOptionsField::default());
+        match options.validate(&index) {
+            Err(Error::UnknownField(field)) => assert_eq!(field, "breadcrumbs"),
+            _ => panic!("expected an unknown field error"),
+        }
+    }
+
     #[test]
     fn test_complex_config() {
         let options = SearchOptions {
-            fields: btreemap! {
+            fields: Some(btreemap! {
                 "title".into() => SearchOptionsField {
                     expand: Some(true),
                     ..Default::default()
@@ -99,9 +187,10 @@ mod tests {
                     boost: Some(200),
                     ..Default::default()
                 },
-            },
+            }),
             expand: false,
             bool: SearchBool::And,
+            fuzzy: None,
         };
         let stringed = serde_json::to_string_pretty(&options).unwrap();
 
@@ -125,4 +214,43 @@ mod tests {
 }"#
         );
     }
+
+    #[test]
+    fn test_fuzzy_config() {
+        let options = SearchOptions {
+            fuzzy: Some(FuzzyOptions {
+                max_edits: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let stringed = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            stringed,
+            r#"{"bool":"OR","expand":false,"fuzzy":{"maxEdits":2,"penalty":50}}"#
+        );
+
+        let parsed: SearchOptions =
+            serde_json::from_str(r#"{"bool":"OR","expand":false,"fuzzy":{"maxEdits":2}}"#).unwrap();
+        assert_eq!(parsed, options);
+    }
+
+    #[test]
+    fn test_fields_config() {
+        let parsed: SearchOptions =
+            serde_json::from_str(r#"{"bool":"OR","expand":false}"#).unwrap();
+        assert_eq!(parsed.fields, None);
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"{"bool":"OR","expand":false}"#
+        );
+
+        let parsed: SearchOptions =
+            serde_json::from_str(r#"{"bool":"OR","expand":false,"fields":{}}"#).unwrap();
+        assert_eq!(parsed.fields, Some(BTreeMap::new()));
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"{"bool":"OR","expand":false,"fields":{}}"#
+        );
+    }
 }
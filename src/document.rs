@@ -0,0 +1,162 @@
+//! Defines the [`Document`] trait for adding typed documents to an [`Index`](crate::Index).
+//!
+//! With the `derive` feature enabled, `Document` can be implemented with
+//! `#[derive(elasticlunr::Document)]`. Each named field of the struct becomes a field of the
+//! index, in declaration order, and can be customized with `#[elasticlunr(...)]` attributes:
+//!
+//! |Attribute               |Effect                                                          |
+//! |------------------------|----------------------------------------------------------------|
+//! |`ref`                   |Use the field as the document reference. Defaults to `id`.      |
+//! |`skip`                  |Neither index nor store the field.                              |
+//! |`store_only`            |Save the field in the document store without indexing it.      |
+//! |`tokenizer = "path"`    |Tokenize the field with the `fn(&str) -> Vec<String>` at `path`.|
+//!
+//! Field values are converted to text with [`FieldValue`].
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use elasticlunr::{Document, Index, IndexBuilder};
+//!
+//! fn split_tags(text: &str) -> Vec<String> {
+//!     text.split(',').map(|tag| tag.trim().to_lowercase()).collect()
+//! }
+//!
+//! #[derive(Document)]
+//! struct Page {
+//!     #[elasticlunr(ref)]
+//!     url: String,
+//!     title: String,
+//!     body: String,
+//!     #[elasticlunr(tokenizer = "split_tags")]
+//!     tags: String,
+//!     #[elasticlunr(store_only)]
+//!     updated: String,
+//!     #[elasticlunr(skip)]
+//!     views: u64,
+//! }
+//!
+//! let mut index = IndexBuilder::new().add_document_fields::<Page>().build();
+//! index.add_document(&Page {
+//!     url: "/intro.html".into(),
+//!     title: "Introduction".into(),
+//!     body: "Welcome to the book".into(),
+//!     tags: "guide, getting started".into(),
+//!     updated: "2024-01-01".into(),
+//!     views: 42,
+//! });
+//! assert_eq!(index.get_fields(), &["title", "body", "tags"]);
+//! # }
+//! ```
+
+use crate::IndexBuilder;
+
+/// A type which can be added to an [`Index`](crate::Index) with
+/// [`Index::add_document`](crate::Index::add_document).
+///
+/// See the [module documentation](self) for deriving this trait.
+pub trait Document {
+    /// Adds the fields of this type to the builder, and sets its reference field.
+    fn add_fields(builder: IndexBuilder) -> IndexBuilder;
+
+    /// Returns the document's reference.
+    fn doc_ref(&self) -> String;
+
+    /// Returns the text of each field, in the same order as they were added by
+    /// [`add_fields`](Document::add_fields).
+    fn field_values(&self) -> Vec<String>;
+
+    /// Returns the name and text of the fields which are saved in the document store without
+    /// being indexed.
+    fn stored_values(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+/// Converts the value of a document field to the text which is indexed.
+///
+/// Like [`Index::add_doc_serde`](crate::Index::add_doc_serde), `None` is converted to an empty
+/// string and the elements of sequences are joined with spaces.
+pub trait FieldValue {
+    fn to_text(&self) -> String;
+}
+
+impl FieldValue for str {
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl FieldValue for String {
+    fn to_text(&self) -> String {
+        self.clone()
+    }
+}
+
+macro_rules! impl_field_value_display {
+    ($($ty:ty),+) => {
+        $(
+            impl FieldValue for $ty {
+                fn to_text(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )+
+    };
+}
+
+impl_field_value_display!(
+    bool, char, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+impl<T: FieldValue + ?Sized> FieldValue for &T {
+    fn to_text(&self) -> String {
+        (**self).to_text()
+    }
+}
+
+impl<T: FieldValue + ?Sized> FieldValue for Box<T> {
+    fn to_text(&self) -> String {
+        (**self).to_text()
+    }
+}
+
+impl<T: FieldValue> FieldValue for Option<T> {
+    fn to_text(&self) -> String {
+        self.as_ref().map_or_else(String::new, FieldValue::to_text)
+    }
+}
+
+impl<T: FieldValue> FieldValue for [T] {
+    fn to_text(&self) -> String {
+        self.iter()
+            .map(FieldValue::to_text)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl<T: FieldValue> FieldValue for Vec<T> {
+    fn to_text(&self) -> String {
+        self.as_slice().to_text()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_values_to_text() {
+        assert_eq!("apple".to_text(), "apple");
+        assert_eq!(String::from("apple").to_text(), "apple");
+        assert_eq!(42u32.to_text(), "42");
+        assert_eq!(true.to_text(), "true");
+        assert_eq!(Some("apple").to_text(), "apple");
+        assert_eq!(None::<String>.to_text(), "");
+        assert_eq!(vec!["apple", "banana"].to_text(), "apple banana");
+        assert_eq!(vec![Some(1), None, Some(3)].to_text(), "1  3");
+    }
+}
@@ -61,6 +61,15 @@ impl DocumentStore {
         }
 
         self.docs.remove(doc_ref);
+        self.doc_info.remove(doc_ref);
+    }
+
+    /// Adds the documents and field lengths of `other` to this store. Documents which are in
+    /// both stores are replaced by the documents from `other`.
+    pub fn merge(&mut self, other: DocumentStore) {
+        self.docs.extend(other.docs);
+        self.doc_info.extend(other.doc_info);
+        self.length = self.docs.len();
     }
 
     pub fn add_field_length(&mut self, doc_ref: &str, field: &str, length: usize) {
@@ -296,6 +305,17 @@ mod tests {
         assert_eq!(store.len(), 0);
     }
 
+    #[test]
+    fn remove_doc_field_length() {
+        let mut store = DocumentStore::new(true);
+
+        store.add_doc("foo", btreemap! { "title".into() => "eggs bread".into() });
+        store.add_field_length("foo", "title", 2);
+        store.remove_doc("foo");
+        assert_eq!(store.get_field_length("foo", "title"), 0);
+        assert!(store.doc_info.is_empty());
+    }
+
     #[test]
     fn remove_nonexistant_store() {
         let mut store = DocumentStore::new(true);
@@ -327,4 +347,25 @@ mod tests {
         assert_eq!(store.get_field_length("foo", "title"), 2);
         assert_eq!(store.get_field_length("foo", "body"), 10);
     }
+
+    #[test]
+    fn merge_stores() {
+        let mut store = DocumentStore::new(true);
+        store.add_doc("foo", btreemap! { "title".into() => "eggs bread".into() });
+        store.add_field_length("foo", "title", 2);
+
+        let mut other = DocumentStore::new(true);
+        other.add_doc("bar", btreemap! { "title".into() => "milk".into() });
+        other.add_field_length("bar", "title", 1);
+
+        store.merge(other);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.length, 2);
+        assert_eq!(
+            store.get_doc("bar").unwrap(),
+            btreemap! { "title".into() => "milk".into() }
+        );
+        assert_eq!(store.get_field_length("foo", "title"), 2);
+        assert_eq!(store.get_field_length("bar", "title"), 1);
+    }
 }
@@ -0,0 +1,130 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// The errors which can be returned by this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A field with the given name was added to an index more than once.
+    DuplicateField(String),
+    /// The field with the given name is not a field of the index.
+    UnknownField(String),
+    /// An index was built without any fields.
+    NoFields,
+    /// A document has more values than the index has fields.
+    TooManyValues { expected: usize, found: usize },
+    /// No [`Language`](crate::Language) with the given name exists.
+    UnknownLanguage(String),
+    /// The [`Language`](crate::Language) with the given name exists, but the cargo feature
+    /// which includes it is not enabled.
+    LanguageNotEnabled { name: String, feature: &'static str },
+    /// No [`PipelineFn`](crate::pipeline::PipelineFn) with the given name exists.
+    UnknownPipelineFunction(String),
+    /// A document is missing the field with the given name.
+    MissingField(String),
+    /// The value of the field with the given name can't be converted to text.
+    InvalidFieldValue(String),
+    /// Two indexes can't be merged because the given settings are different.
+    IncompatibleIndexes(&'static str),
+    /// A document with the given reference is already in the index.
+    DuplicateDocument(String),
+    /// A phrase was searched in an index which doesn't store token positions.
+    PositionsNotStored,
+    /// The token positions of the document with the given reference are not in ascending order.
+    UnsortedPositions(String),
+    /// A document did not serialize to a map of field names to values.
+    InvalidDocument,
+    /// The index could not be serialized or deserialized.
+    Serialization(serde_json::Error),
+    /// The index could not be read or written.
+    Io(io::Error),
+    /// The data is not an index in the [binary format](crate::binary), or is corrupted.
+    InvalidBinary(&'static str),
+    /// The index is in a version of the [binary format](crate::binary) which this version of
+    /// the crate can't read.
+    UnsupportedBinaryVersion(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::DuplicateField(field) => write!(f, "Duplicate fields in index: {}", field),
+            Error::UnknownField(field) => write!(f, "Unknown field: {}", field),
+            Error::NoFields => write!(f, "The index has no fields"),
+            Error::TooManyValues { expected, found } => write!(
+                f,
+                "Too many values for the fields of the index: expected at most {}, found {}",
+                expected, found
+            ),
+            Error::UnknownLanguage(name) => write!(f, "Unknown language name: {}", name),
+            Error::LanguageNotEnabled { name, feature } => write!(
+                f,
+                "The {} language requires the `{}` feature to be enabled",
+                name, feature
+            ),
+            Error::UnknownPipelineFunction(name) => {
+                write!(f, "Unknown pipeline function: {}", name)
+            }
+            Error::MissingField(field) => write!(f, "Missing field: {}", field),
+            Error::InvalidFieldValue(field) => {
+                write!(f, "The value of field {} can't be converted to text", field)
+            }
+            Error::IncompatibleIndexes(setting) => write!(
+                f,
+                "The indexes can't be merged because their {} are different",
+                setting
+            ),
+            Error::DuplicateDocument(doc_ref) => {
+                write!(f, "Duplicate document reference: {}", doc_ref)
+            }
+            Error::PositionsNotStored => write!(
+                f,
+                "Phrase searches need an index which stores token positions"
+            ),
+            Error::UnsortedPositions(doc_ref) => write!(
+                f,
+                "The token positions of document {} are not in ascending order",
+                doc_ref
+            ),
+            Error::InvalidDocument => {
+                write!(
+                    f,
+                    "Documents must serialize to a map of field names to values"
+                )
+            }
+            Error::Serialization(err) => write!(f, "Serialization error: {}", err),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::InvalidBinary(reason) => write!(f, "Invalid binary index: {}", reason),
+            Error::UnsupportedBinaryVersion(version) => {
+                write!(f, "Unsupported binary index version: {}", version)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Serialization(err) => Some(err),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            Error::Io(err.into())
+        } else {
+            Error::Serialization(err)
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
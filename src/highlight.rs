@@ -0,0 +1,264 @@
+//! Finds the text matched by a query in the documents of an [`Index`], for highlighting search
+//! results. Most users only need [`Index::highlight`].
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::Index;
+
+/// The tokens of a field of a document, and which of them match a query. Returned by
+/// [`Index::highlight`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight<'a> {
+    text: &'a str,
+    tokens: Vec<(Range<usize>, bool)>,
+}
+
+impl<'a> Highlight<'a> {
+    /// The text of the field.
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// The byte ranges of the text which match the query, in order.
+    pub fn matches(&self) -> Vec<Range<usize>> {
+        self.tokens
+            .iter()
+            .filter(|(_, matched)| *matched)
+            .map(|(range, _)| range.clone())
+            .collect()
+    }
+
+    /// Returns the text with each match surrounded by `open` and `close`.
+    pub fn marked(&self, open: &str, close: &str) -> String {
+        self.mark(0..self.tokens.len(), open, close)
+    }
+
+    /// Returns an excerpt of `words` tokens of the text around the best match, with each match
+    /// surrounded by `open` and `close`. The best match is the part of the text which contains
+    /// the most matches, and the excerpt starts with `...` or ends with `...` if it does not
+    /// include the start or the end of the text.
+    ///
+    /// Tokens removed by the pipeline, like stop words, are counted as words.
+    pub fn snippet(&self, words: usize, open: &str, close: &str) -> String {
+        let words = words.min(self.tokens.len());
+        if words == 0 {
+            return String::new();
+        }
+
+        let mut best = 0;
+        let mut best_count = 0;
+        let mut count = 0;
+        for (i, (_, matched)) in self.tokens.iter().enumerate() {
+            count += usize::from(*matched);
+            if i >= words {
+                count -= usize::from(self.tokens[i - words].1);
+            }
+            if i + 1 >= words && count > best_count {
+                best = i + 1 - words;
+                best_count = count;
+            }
+        }
+
+        let tokens = best..best + words;
+        let mut snippet = String::new();
+        if best > 0 {
+            snippet.push_str("...");
+        }
+        snippet.push_str(&self.mark(tokens.clone(), open, close));
+        if tokens.end < self.tokens.len() {
+            snippet.push_str("...");
+        }
+        snippet
+    }
+
+    /// Marks the matches in the text from the start of the first token in `tokens` to the end of
+    /// the last one, or in all of the text if `tokens` is every token.
+    fn mark(&self, tokens: Range<usize>, open: &str, close: &str) -> String {
+        let (mut start, end) = if tokens.start == 0 && tokens.end == self.tokens.len() {
+            (0, self.text.len())
+        } else {
+            (
+                self.tokens[tokens.start].0.start,
+                self.tokens[tokens.end - 1].0.end,
+            )
+        };
+
+        let mut marked = String::new();
+        for (range, _) in self.tokens[tokens].iter().filter(|(_, matched)| *matched) {
+            marked.push_str(&self.text[start..range.start]);
+            marked.push_str(open);
+            marked.push_str(&self.text[range.clone()]);
+            marked.push_str(close);
+            start = range.end;
+        }
+        marked.push_str(&self.text[start..end]);
+        marked
+    }
+}
+
+impl Index {
+    /// Finds the tokens in `field` of the document which match `query`, for highlighting a
+    /// search result. Returns `None` if the text of the field is not in the document store,
+    /// either because the document or the field doesn't exist, or because the index was built
+    /// with [`save_docs(false)`](crate::IndexBuilder::save_docs).
+    ///
+    /// The text and the query are tokenized and processed by the pipeline the same way as when
+    /// the document was indexed, so a query for "apple" highlights "Apples".
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["Fruit", "Apples and oranges are both fruits"]);
+    ///
+    /// let highlight = index.highlight("1", "body", "apple fruit").unwrap();
+    /// assert_eq!(highlight.matches(), [0..6, 28..34]);
+    /// assert_eq!(
+    ///     highlight.marked("<b>", "</b>"),
+    ///     "<b>Apples</b> and oranges are both <b>fruits</b>"
+    /// );
+    /// assert_eq!(highlight.snippet(3, "<b>", "</b>"), "<b>Apples</b> and oranges...");
+    /// ```
+    pub fn highlight(&self, doc_ref: &str, field: &str, query: &str) -> Option<Highlight<'_>> {
+        let text = self.document_store.docs.get(doc_ref)?.get(field)?;
+
+        let query_tokens: BTreeSet<_> = self
+            .pipeline
+            .run(self.raw_query_tokens(field, query))
+            .into_iter()
+            .collect();
+
+        let raw_tokens = self.raw_query_tokens(field, text);
+        let mut tokens: Vec<_> = token_ranges(text, &raw_tokens)
+            .into_iter()
+            .map(|range| (range, false))
+            .collect();
+        for (position, token) in self.pipeline.run_with_positions(raw_tokens) {
+            tokens[position].1 = query_tokens.contains(&token);
+        }
+
+        Some(Highlight {
+            text,
+            tokens: tokens
+                .into_iter()
+                .filter_map(|(range, matched)| Some((range?, matched)))
+                .collect(),
+        })
+    }
+}
+
+/// Finds the byte range of each token in `text`. Tokenizers usually lowercase the text, so the
+/// tokens are searched for in order in a lowercased copy of it. Tokens which can't be found,
+/// because the tokenizer changed them some other way, have no range.
+pub(crate) fn token_ranges(text: &str, tokens: &[String]) -> Vec<Option<Range<usize>>> {
+    // Lowercasing can change the length of a character, so remember where each byte of the
+    // lowercased text came from.
+    let mut lower = String::with_capacity(text.len());
+    let mut char_ranges = Vec::with_capacity(text.len());
+    for (i, ch) in text.char_indices() {
+        for lower_ch in ch.to_lowercase() {
+            lower.push(lower_ch);
+            char_ranges.resize(lower.len(), i..i + ch.len_utf8());
+        }
+    }
+
+    let mut cursor = 0;
+    tokens
+        .iter()
+        .map(|token| {
+            if token.is_empty() {
+                return None;
+            }
+            let start = cursor + lower[cursor..].find(token.as_str())?;
+            let end = start + token.len();
+            cursor = end;
+            Some(char_ranges[start].start..char_ranges[end - 1].end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexBuilder;
+
+    #[test]
+    fn finding_token_ranges() {
+        let tokens = ["the", "cafe", "café", "straße", "the"].map(String::from);
+        assert_eq!(
+            token_ranges("The CAFE, Café STRASSE straße... THE", &tokens),
+            vec![
+                Some(0..3),
+                Some(4..8),
+                Some(10..15),
+                Some(24..31),
+                Some(35..38)
+            ]
+        );
+        assert_eq!(
+            token_ranges(
+                "İstanbul and kaya",
+                &["i̇stanbul", "x", "kaya"].map(String::from)
+            ),
+            vec![Some(0..9), None, Some(14..18)]
+        );
+    }
+
+    #[test]
+    fn highlighting() {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc(
+            "1",
+            &[
+                "Apple pie",
+                "An apple a day keeps the doctor away, but a pie of apples is even better.",
+            ],
+        );
+
+        let highlight = index.highlight("1", "body", "apples pie").unwrap();
+        assert_eq!(highlight.matches(), [3..8, 44..47, 51..57]);
+        assert_eq!(
+            highlight.marked("[", "]"),
+            "An [apple] a day keeps the doctor away, but a [pie] of [apples] is even better."
+        );
+        assert_eq!(
+            highlight.snippet(5, "[", "]"),
+            "...but a [pie] of [apples]..."
+        );
+        assert_eq!(highlight.snippet(100, "[", "]"), highlight.marked("[", "]"));
+        assert_eq!(highlight.snippet(0, "[", "]"), "");
+
+        let highlight = index.highlight("1", "body", "banana").unwrap();
+        assert!(highlight.matches().is_empty());
+        assert_eq!(highlight.snippet(3, "[", "]"), "An apple a...");
+
+        assert!(index.highlight("2", "body", "apple").is_none());
+        assert!(index.highlight("1", "tags", "apple").is_none());
+
+        let mut index = IndexBuilder::new()
+            .save_docs(false)
+            .add_field("body")
+            .build();
+        index.add_doc("1", &["apple"]);
+        assert!(index.highlight("1", "body", "apple").is_none());
+    }
+
+    #[test]
+    fn highlighting_with_field_tokenizer() {
+        fn split_commas(text: &str) -> Vec<String> {
+            text.split(',')
+                .map(|tag| tag.trim().to_lowercase())
+                .collect()
+        }
+
+        let mut index = IndexBuilder::new()
+            .add_field_with_tokenizer("tags", Box::new(split_commas))
+            .build();
+        index.add_doc("1", &["New York, Old York, york"]);
+
+        let highlight = index.highlight("1", "tags", "new york").unwrap();
+        assert_eq!(highlight.marked("<", ">"), "<New York>, Old York, york");
+    }
+}
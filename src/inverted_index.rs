@@ -1,11 +1,38 @@
 //! Implements an elasticlunr.js inverted index. Most users do not need to use this module directly.
 
-use std::collections::BTreeMap;
+use std::collections::{btree_map, BTreeMap};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+use crate::binary::{Decoder, Encoder};
+use crate::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct TermFrequency {
     #[serde(rename = "tf")]
     pub term_freq: f64,
+    // Only stored when enabled with `IndexBuilder::store_positions`, so that indexes without
+    // positions stay compatible with elasticlunr.js. Always sorted and without duplicates.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_positions"
+    )]
+    pub positions: Vec<usize>,
+}
+
+fn deserialize_positions<'de, D>(deserializer: D) -> Result<Vec<usize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut positions: Vec<usize> = serde::Deserialize::deserialize(deserializer)?;
+    sort_positions(&mut positions);
+    Ok(positions)
+}
+
+fn sort_positions(positions: &mut Vec<usize>) {
+    positions.sort_unstable();
+    positions.dedup();
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
@@ -33,7 +60,7 @@ impl IndexItem {
         ser_map.end()
     }
 
-    fn add_token(&mut self, doc_ref: &str, token: &str, term_freq: f64) {
+    fn add_token(&mut self, doc_ref: &str, token: &str, term_freq: TermFrequency) {
         let mut iter = token.chars();
         if let Some(character) = iter.next() {
             let mut item = self.children.entry(character).or_default();
@@ -46,8 +73,7 @@ impl IndexItem {
             if !item.docs.contains_key(doc_ref) {
                 item.doc_freq += 1;
             }
-            item.docs
-                .insert(doc_ref.into(), TermFrequency { term_freq });
+            item.docs.insert(doc_ref.into(), term_freq);
         }
     }
 
@@ -60,6 +86,56 @@ impl IndexItem {
         Some(root)
     }
 
+    /// Calls `f` with every token which has postings in the subtree of this node, in lexical
+    /// order. `token` is the token of this node, and is restored before returning.
+    fn for_each_token<'a, F>(&'a self, token: &mut String, f: &mut F)
+    where
+        F: FnMut(&str, &'a IndexItem),
+    {
+        if self.doc_freq > 0 {
+            f(token, self);
+        }
+
+        for (&ch, item) in &self.children {
+            token.push(ch);
+            item.for_each_token(token, f);
+            token.pop();
+        }
+    }
+
+    /// Finds the tokens below this node within `max_edits` of `term`. `row` holds the
+    /// Levenshtein distances between `token`, the token of this node, and each prefix of `term`.
+    fn fuzzy_terms(
+        &self,
+        term: &[char],
+        max_edits: usize,
+        token: &mut String,
+        row: &[usize],
+        matches: &mut Vec<(String, usize)>,
+    ) {
+        for (&ch, item) in &self.children {
+            let mut next_row = Vec::with_capacity(row.len());
+            next_row.push(row[0] + 1);
+            for (i, &term_ch) in term.iter().enumerate() {
+                let substitution = row[i] + usize::from(term_ch != ch);
+                let insertion = next_row[i] + 1;
+                let deletion = row[i + 1] + 1;
+                next_row.push(substitution.min(insertion).min(deletion));
+            }
+
+            token.push(ch);
+            let distance = next_row[term.len()];
+            if item.doc_freq > 0 && distance <= max_edits {
+                matches.push((token.clone(), distance));
+            }
+            // Distances only grow further down the trie.
+            if next_row.iter().any(|&edits| edits <= max_edits) {
+                item.fuzzy_terms(term, max_edits, token, &next_row, matches);
+            }
+            token.pop();
+        }
+    }
+
     fn remove_token(&mut self, doc_ref: &str, token: &str) {
         let mut iter = token.char_indices();
         if let Some((_, ch)) = iter.next() {
@@ -73,6 +149,164 @@ impl IndexItem {
             }
         }
     }
+
+    fn merge(&mut self, other: IndexItem) {
+        for (doc_ref, term_freq) in other.docs {
+            if self.docs.insert(doc_ref, term_freq).is_none() {
+                self.doc_freq += 1;
+            }
+        }
+
+        for (ch, other_item) in other.children {
+            self.children.entry(ch).or_default().merge(other_item);
+        }
+    }
+
+    fn for_each_posting<'a, F>(&'a self, f: &mut F)
+    where
+        F: FnMut(&'a str),
+    {
+        for doc_ref in self.docs.keys() {
+            f(doc_ref);
+        }
+        for item in self.children.values() {
+            item.for_each_posting(f);
+        }
+    }
+
+    fn encode<W: Write>(
+        &self,
+        enc: &mut Encoder<W>,
+        refs: &BTreeMap<&str, u64>,
+        positions: bool,
+    ) -> Result<(), Error> {
+        enc.varint(self.docs.len() as u64)?;
+        let mut prev_ref = 0;
+        for (doc_ref_str, term_freq) in &self.docs {
+            let doc_ref = refs[doc_ref_str.as_str()];
+            enc.varint(doc_ref - prev_ref)?;
+            prev_ref = doc_ref;
+
+            // Term frequencies are almost always the square root of a token count.
+            let count = (term_freq.term_freq * term_freq.term_freq).round();
+            if count >= 1. && count < 2f64.powi(53) && count.sqrt() == term_freq.term_freq {
+                enc.varint(count as u64)?;
+            } else {
+                enc.varint(0)?;
+                enc.f64(term_freq.term_freq)?;
+            }
+
+            if positions {
+                enc.varint(term_freq.positions.len() as u64)?;
+                let mut prev_position = 0;
+                for &position in &term_freq.positions {
+                    let delta = position
+                        .checked_sub(prev_position)
+                        .ok_or_else(|| Error::UnsortedPositions(doc_ref_str.clone()))?;
+                    enc.varint(delta as u64)?;
+                    prev_position = position;
+                }
+            }
+        }
+
+        enc.varint(self.children.len() as u64)?;
+        for (&ch, item) in &self.children {
+            enc.varint(u64::from(ch))?;
+            item.encode(enc, refs, positions)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read>(
+        dec: &mut Decoder<R>,
+        refs: &[String],
+        positions: bool,
+    ) -> Result<Self, Error> {
+        let mut item = IndexItem::default();
+        let mut doc_ref = 0usize;
+        for _ in 0..dec.varint()? {
+            doc_ref = doc_ref
+                .checked_add(dec.usize()?)
+                .filter(|&doc_ref| doc_ref < refs.len())
+                .ok_or(Error::InvalidBinary("reference to a missing string"))?;
+
+            let term_freq = match dec.varint()? {
+                0 => dec.f64()?,
+                count => (count as f64).sqrt(),
+            };
+
+            let mut term_positions = Vec::new();
+            if positions {
+                let mut position = 0usize;
+                for _ in 0..dec.varint()? {
+                    position = position
+                        .checked_add(dec.usize()?)
+                        .ok_or(Error::InvalidBinary("integer out of range"))?;
+                    term_positions.push(position);
+                }
+            }
+
+            item.docs.insert(
+                refs[doc_ref].clone(),
+                TermFrequency {
+                    term_freq,
+                    positions: term_positions,
+                },
+            );
+        }
+        item.doc_freq = item.docs.len() as i64;
+
+        for _ in 0..dec.varint()? {
+            let ch = u32::try_from(dec.varint()?)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or(Error::InvalidBinary("invalid character"))?;
+            let child = IndexItem::decode(dec, refs, positions)?;
+            item.children.insert(ch, child);
+        }
+        Ok(item)
+    }
+
+    fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .values()
+            .map(IndexItem::node_count)
+            .sum::<usize>()
+    }
+
+    /// Removes the posting of `doc_ref` for `token`, along with the nodes on its path which no
+    /// longer have postings or children.
+    fn remove_posting(&mut self, doc_ref: &str, token: &str) {
+        let mut chars = token.chars();
+        match chars.next() {
+            Some(ch) => {
+                if let Some(item) = self.children.get_mut(&ch) {
+                    item.remove_posting(doc_ref, chars.as_str());
+                    if item.docs.is_empty() && item.children.is_empty() {
+                        self.children.remove(&ch);
+                    }
+                }
+            }
+            None => {
+                if self.docs.remove(doc_ref).is_some() {
+                    self.doc_freq -= 1;
+                }
+            }
+        }
+    }
+
+    fn remove_doc(&mut self, doc_ref: &str) {
+        if self.docs.remove(doc_ref).is_some() {
+            self.doc_freq -= 1;
+        }
+
+        for item in self.children.values_mut() {
+            item.remove_doc(doc_ref);
+        }
+        self.children
+            .retain(|_, item| !item.docs.is_empty() || !item.children.is_empty());
+    }
 }
 
 /// Implements an elasticlunr.js inverted index. Most users do not need to use this type directly.
@@ -87,6 +321,27 @@ impl InvertedIndex {
     }
 
     pub fn add_token(&mut self, doc_ref: &str, token: &str, term_freq: f64) {
+        let term_freq = TermFrequency {
+            term_freq,
+            positions: Vec::new(),
+        };
+        self.root.add_token(doc_ref, token, term_freq)
+    }
+
+    /// Adds a token like [`add_token`](#method.add_token), along with the positions of the token
+    /// in the document. The positions are sorted, and duplicate positions are removed.
+    pub fn add_token_with_positions(
+        &mut self,
+        doc_ref: &str,
+        token: &str,
+        term_freq: f64,
+        mut positions: Vec<usize>,
+    ) {
+        sort_positions(&mut positions);
+        let term_freq = TermFrequency {
+            term_freq,
+            positions,
+        };
         self.root.add_token(doc_ref, token, term_freq)
     }
 
@@ -98,15 +353,203 @@ impl InvertedIndex {
         self.root.remove_token(doc_ref, token)
     }
 
+    /// Removes every posting of `doc_ref`, along with any tokens which no longer have postings.
+    ///
+    /// Unlike [`remove_token`](#method.remove_token), this does not need to know which tokens
+    /// the document contained, but it has to visit every token in the index.
+    pub fn remove_doc(&mut self, doc_ref: &str) {
+        self.root.remove_doc(doc_ref)
+    }
+
+    /// Removes the postings of `doc_ref` for `tokens`, along with any of these tokens which no
+    /// longer have postings. This leaves the index like [`remove_doc`](#method.remove_doc) if
+    /// `tokens` are all the tokens of the document, but only visits those tokens.
+    pub fn remove_doc_tokens<I>(&mut self, doc_ref: &str, tokens: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        for token in tokens {
+            self.root.remove_posting(doc_ref, token.as_ref());
+        }
+    }
+
+    /// Adds every posting of `other` to this index. Postings of documents which are in both
+    /// indexes are replaced by the postings from `other`.
+    pub fn merge(&mut self, other: InvertedIndex) {
+        self.root.merge(other.root)
+    }
+
+    /// Splits the index into smaller indexes, keyed by the first `len` characters of the tokens
+    /// they contain. Tokens shorter than `len` characters are keyed by the whole token, and a
+    /// `len` of 0 puts every token in one index keyed by the empty string.
+    ///
+    /// Merging the indexes with [`merge`](#method.merge) gives back this index.
+    pub fn split_by_prefix(&self, len: usize) -> BTreeMap<String, InvertedIndex> {
+        let mut indexes = BTreeMap::new();
+        self.root
+            .for_each_token(&mut String::new(), &mut |token, item| {
+                let key: String = token.chars().take(len).collect();
+                let index: &mut InvertedIndex = indexes.entry(key).or_default();
+                for (doc_ref, term_freq) in &item.docs {
+                    index.root.add_token(doc_ref, token, term_freq.clone());
+                }
+            });
+        indexes
+    }
+
+    /// Calls `f` with the document reference of every posting in the index.
+    pub(crate) fn for_each_posting<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(&'a str),
+    {
+        self.root.for_each_posting(&mut f)
+    }
+
+    /// Calls `f` with every token in the index, in lexical order, along with each document
+    /// containing it, its term frequency and its positions in the document.
+    pub(crate) fn for_each_token<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(&str, &'a str, f64, &'a [usize]),
+    {
+        self.root
+            .for_each_token(&mut String::new(), &mut |token, item: &'a IndexItem| {
+                for (doc_ref, term_freq) in &item.docs {
+                    f(token, doc_ref, term_freq.term_freq, &term_freq.positions);
+                }
+            });
+    }
+
+    /// Returns the number of nodes in the trie of the index, including the root.
+    pub(crate) fn node_count(&self) -> usize {
+        self.root.node_count()
+    }
+
+    /// Writes the index in the [binary format](crate::binary). `refs` maps each document
+    /// reference to its index in the table of references.
+    pub(crate) fn encode<W: Write>(
+        &self,
+        enc: &mut Encoder<W>,
+        refs: &BTreeMap<&str, u64>,
+        positions: bool,
+    ) -> Result<(), Error> {
+        self.root.encode(enc, refs, positions)
+    }
+
+    /// Reads an index written by [`encode`](#method.encode).
+    pub(crate) fn decode<R: Read>(
+        dec: &mut Decoder<R>,
+        refs: &[String],
+        positions: bool,
+    ) -> Result<Self, Error> {
+        Ok(InvertedIndex {
+            root: IndexItem::decode(dec, refs, positions)?,
+        })
+    }
+
     pub fn get_docs(&self, token: &str) -> Option<BTreeMap<String, f64>> {
         self.root.get_node(token).map(|node| {
             node.docs
                 .iter()
-                .map(|(k, &v)| (k.clone(), v.term_freq))
+                .map(|(k, v)| (k.clone(), v.term_freq))
                 .collect()
         })
     }
 
+    /// Returns every token in the index which starts with `prefix`, including `prefix` itself,
+    /// in lexical order.
+    ///
+    /// Like elasticlunr.js's `expandToken`, an empty prefix expands to nothing rather than to
+    /// every token in the index.
+    pub fn expand_token(&self, prefix: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        self.walk_prefix(prefix, |token, _| tokens.push(token.to_string()));
+        tokens
+    }
+
+    /// Returns the documents and term frequencies of every token which starts with `prefix`,
+    /// keyed by token. This is [`expand_token`](#method.expand_token) combined with
+    /// [`get_docs`](#method.get_docs).
+    pub fn postings_with_prefix(&self, prefix: &str) -> BTreeMap<String, BTreeMap<String, f64>> {
+        let mut postings = BTreeMap::new();
+        self.walk_prefix(prefix, |token, item| {
+            let docs = item
+                .docs
+                .iter()
+                .map(|(k, v)| (k.clone(), v.term_freq))
+                .collect();
+            postings.insert(token.to_string(), docs);
+        });
+        postings
+    }
+
+    fn walk_prefix<F>(&self, prefix: &str, mut f: F)
+    where
+        F: FnMut(&str, &IndexItem),
+    {
+        if prefix.is_empty() {
+            return;
+        }
+        if let Some(node) = self.root.get_node(prefix) {
+            node.for_each_token(&mut prefix.to_string(), &mut f);
+        }
+    }
+
+    /// Returns every token in the index within `max_edits` insertions, deletions or
+    /// substitutions of `term`, along with its edit distance, in lexical order. This includes
+    /// `term` itself, at a distance of 0.
+    ///
+    /// Like [`expand_token`](#method.expand_token), an empty term matches nothing.
+    pub fn fuzzy_terms(&self, term: &str, max_edits: usize) -> Vec<(String, usize)> {
+        let mut matches = Vec::new();
+        if term.is_empty() {
+            return matches;
+        }
+
+        let term: Vec<char> = term.chars().collect();
+        let row: Vec<usize> = (0..=term.len()).collect();
+        self.root
+            .fuzzy_terms(&term, max_edits, &mut String::new(), &row, &mut matches);
+        matches
+    }
+
+    /// Returns an iterator over every token in the index and its document frequency, in
+    /// lexical order.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::inverted_index::InvertedIndex;
+    /// let mut index = InvertedIndex::new();
+    /// index.add_token("1", "foo", 1.);
+    /// index.add_token("2", "foo", 1.);
+    /// index.add_token("2", "bar", 1.);
+    ///
+    /// let terms: Vec<_> = index.terms().collect();
+    /// assert_eq!(terms, [("bar".to_string(), 1), ("foo".to_string(), 2)]);
+    /// ```
+    pub fn terms(&self) -> Terms<'_> {
+        Terms(TrieWalk::new(&self.root))
+    }
+
+    /// Returns an iterator over every token in the index, in lexical order, along with an
+    /// iterator over the documents containing it and their term frequencies.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::inverted_index::InvertedIndex;
+    /// let mut index = InvertedIndex::new();
+    /// index.add_token("1", "foo", 1.);
+    /// index.add_token("2", "foo", 2.);
+    ///
+    /// for (token, docs) in index.postings() {
+    ///     assert_eq!(token, "foo");
+    ///     assert_eq!(docs.collect::<Vec<_>>(), [("1", 1.), ("2", 2.)]);
+    /// }
+    /// ```
+    pub fn postings(&self) -> Postings<'_> {
+        Postings(TrieWalk::new(&self.root))
+    }
+
     pub fn get_term_frequency(&self, doc_ref: &str, token: &str) -> f64 {
         self.root
             .get_node(token)
@@ -117,8 +560,107 @@ impl InvertedIndex {
     pub fn get_doc_frequency(&self, token: &str) -> i64 {
         self.root.get_node(token).map_or(0, |node| node.doc_freq)
     }
+
+    /// Returns the positions of `token` in the document, or an empty slice if the token isn't
+    /// in the document or its positions weren't stored.
+    pub fn get_positions(&self, doc_ref: &str, token: &str) -> &[usize] {
+        self.root
+            .get_node(token)
+            .and_then(|node| node.docs.get(doc_ref))
+            .map_or(&[], |docs| &docs.positions)
+    }
+}
+
+/// Walks the trie depth first, returning each node with postings along with its token. Children
+/// are visited in order of their characters, so the tokens are in lexical order.
+#[derive(Clone, Debug)]
+struct TrieWalk<'a> {
+    token: String,
+    // The children left to visit at each level. The token has a character for each level but the
+    // first.
+    stack: Vec<btree_map::Iter<'a, char, IndexItem>>,
+}
+
+impl<'a> TrieWalk<'a> {
+    fn new(root: &'a IndexItem) -> Self {
+        TrieWalk {
+            token: String::new(),
+            stack: vec![root.children.iter()],
+        }
+    }
 }
 
+impl<'a> Iterator for TrieWalk<'a> {
+    type Item = (String, &'a IndexItem);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some((&ch, item)) => {
+                    self.token.push(ch);
+                    self.stack.push(item.children.iter());
+                    if item.doc_freq > 0 {
+                        return Some((self.token.clone(), item));
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                    self.token.pop();
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the tokens of an [`InvertedIndex`] and their document frequencies, in
+/// lexical order. Returned by [`InvertedIndex::terms`].
+#[derive(Clone, Debug)]
+pub struct Terms<'a>(TrieWalk<'a>);
+
+impl<'a> Iterator for Terms<'a> {
+    type Item = (String, i64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(token, item)| (token, item.doc_freq))
+    }
+}
+
+/// An iterator over the tokens of an [`InvertedIndex`] and the documents containing them, in
+/// lexical order. Returned by [`InvertedIndex::postings`].
+#[derive(Clone, Debug)]
+pub struct Postings<'a>(TrieWalk<'a>);
+
+impl<'a> Iterator for Postings<'a> {
+    type Item = (String, TokenPostings<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(token, item)| (token, TokenPostings(item.docs.iter())))
+    }
+}
+
+/// An iterator over the documents containing a token and the term frequency of the token in
+/// each of them, in order of document reference. Returned by [`Postings`].
+#[derive(Clone, Debug)]
+pub struct TokenPostings<'a>(btree_map::Iter<'a, String, TermFrequency>);
+
+impl<'a> Iterator for TokenPostings<'a> {
+    type Item = (&'a str, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(doc_ref, term_freq)| (doc_ref.as_str(), term_freq.term_freq))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for TokenPostings<'_> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -315,6 +857,276 @@ mod tests {
         assert_eq!(inverted_index.get_doc_frequency("foo"), 0);
     }
 
+    #[test]
+    fn removing_document_from_all_tokens() {
+        let mut inverted_index = InvertedIndex::new();
+
+        inverted_index.add_token("123", "foo", 1.);
+        inverted_index.add_token("456", "foo", 1.);
+        inverted_index.add_token("123", "food", 1.);
+        inverted_index.add_token("123", "bar", 1.);
+        inverted_index.remove_doc("123");
+
+        assert_eq!(
+            inverted_index.get_docs("foo").unwrap(),
+            btreemap! {
+                "456".into() => 1.
+            }
+        );
+        assert_eq!(inverted_index.get_doc_frequency("foo"), 1);
+        assert!(!inverted_index.has_token("food"));
+        assert!(!inverted_index.has_token("b"));
+
+        inverted_index.remove_doc("456");
+        assert_eq!(inverted_index, InvertedIndex::new());
+    }
+
+    #[test]
+    fn removing_document_tokens() {
+        let mut inverted_index = InvertedIndex::new();
+        inverted_index.add_token("123", "foo", 1.);
+        inverted_index.add_token("456", "foo", 1.);
+        inverted_index.add_token("123", "food", 1.);
+        inverted_index.add_token("456", "fo", 1.);
+        inverted_index.add_token("123", "bar", 1.);
+
+        let mut expected = InvertedIndex::new();
+        expected.add_token("456", "foo", 1.);
+        expected.add_token("456", "fo", 1.);
+
+        inverted_index.remove_doc_tokens("123", &["foo", "food", "bar", "missing"]);
+        assert_eq!(inverted_index, expected);
+
+        inverted_index.remove_doc_tokens("456", &["fo", "foo"]);
+        assert_eq!(inverted_index, InvertedIndex::new());
+    }
+
+    #[test]
+    fn expanding_token() {
+        let mut inverted_index = InvertedIndex::new();
+
+        inverted_index.add_token("123", "hello", 1.);
+        inverted_index.add_token("234", "hell", 1.);
+        inverted_index.add_token("345", "help", 1.);
+        inverted_index.add_token("456", "he", 1.);
+        inverted_index.add_token("456", "foo", 1.);
+
+        assert_eq!(
+            inverted_index.expand_token("hel"),
+            vec!["hell", "hello", "help"]
+        );
+        assert_eq!(
+            inverted_index.expand_token("he"),
+            vec!["he", "hell", "hello", "help"]
+        );
+        assert_eq!(inverted_index.expand_token("hello"), vec!["hello"]);
+        assert!(inverted_index.expand_token("hellos").is_empty());
+        assert!(inverted_index.expand_token("bar").is_empty());
+        assert!(inverted_index.expand_token("").is_empty());
+
+        inverted_index.remove_token("234", "hell");
+        assert_eq!(inverted_index.expand_token("hel"), vec!["hello", "help"]);
+    }
+
+    #[test]
+    fn iterating_terms_and_postings() {
+        let mut inverted_index = InvertedIndex::new();
+        inverted_index.add_token("1", "foo", 1.);
+        inverted_index.add_token("2", "foo", 2.);
+        inverted_index.add_token("1", "fo", 1.);
+        inverted_index.add_token("2", "food", 3.);
+        inverted_index.add_token("1", "bar", 1.);
+        inverted_index.add_token("1", "\u{e9}t\u{e9}", 1.);
+        // The empty node left behind is skipped.
+        inverted_index.add_token("1", "baz", 1.);
+        inverted_index.remove_token("1", "baz");
+
+        let terms: Vec<_> = inverted_index.terms().collect();
+        assert_eq!(
+            terms,
+            [
+                ("bar".to_string(), 1),
+                ("fo".to_string(), 1),
+                ("foo".to_string(), 2),
+                ("food".to_string(), 1),
+                ("\u{e9}t\u{e9}".to_string(), 1),
+            ]
+        );
+
+        let postings: Vec<_> = inverted_index
+            .postings()
+            .map(|(token, docs)| (token, docs.collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(postings[2], ("foo".to_string(), vec![("1", 1.), ("2", 2.)]));
+        assert_eq!(postings[3], ("food".to_string(), vec![("2", 3.)]));
+
+        let mut expected = Vec::new();
+        inverted_index.for_each_token(|token, doc_ref, term_freq, _| {
+            expected.push((token.to_string(), doc_ref, term_freq));
+        });
+        let flattened: Vec<_> = inverted_index
+            .postings()
+            .flat_map(|(token, docs)| docs.map(move |(doc_ref, tf)| (token.clone(), doc_ref, tf)))
+            .collect();
+        assert_eq!(flattened, expected);
+
+        assert_eq!(InvertedIndex::new().terms().next(), None);
+    }
+
+    #[test]
+    fn postings_with_prefix() {
+        let mut inverted_index = InvertedIndex::new();
+
+        inverted_index.add_token("123", "hello", 1.);
+        inverted_index.add_token("234", "hello", 2.);
+        inverted_index.add_token("234", "help", 3.);
+        inverted_index.add_token("345", "foo", 1.);
+
+        assert_eq!(
+            inverted_index.postings_with_prefix("hel"),
+            btreemap! {
+                "hello".into() => btreemap! {
+                    "123".into() => 1.,
+                    "234".into() => 2.,
+                },
+                "help".into() => btreemap! {
+                    "234".into() => 3.,
+                },
+            }
+        );
+        assert!(inverted_index.postings_with_prefix("bar").is_empty());
+        assert!(inverted_index.postings_with_prefix("").is_empty());
+    }
+
+    #[test]
+    fn fuzzy_terms() {
+        let mut inverted_index = InvertedIndex::new();
+
+        inverted_index.add_token("123", "hello", 1.);
+        inverted_index.add_token("234", "hell", 1.);
+        inverted_index.add_token("345", "help", 1.);
+        inverted_index.add_token("456", "yellow", 1.);
+        inverted_index.add_token("456", "jello", 1.);
+
+        assert_eq!(
+            inverted_index.fuzzy_terms("hello", 1),
+            vec![
+                ("hell".to_string(), 1),
+                ("hello".to_string(), 0),
+                ("jello".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            inverted_index.fuzzy_terms("hello", 2),
+            vec![
+                ("hell".to_string(), 1),
+                ("hello".to_string(), 0),
+                ("help".to_string(), 2),
+                ("jello".to_string(), 1),
+                ("yellow".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            inverted_index.fuzzy_terms("hlelo", 2),
+            vec![
+                ("hell".to_string(), 2),
+                ("hello".to_string(), 2),
+                ("help".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            inverted_index.fuzzy_terms("help", 0),
+            vec![("help".to_string(), 0)]
+        );
+        assert!(inverted_index.fuzzy_terms("world", 2).is_empty());
+        assert!(inverted_index.fuzzy_terms("", 2).is_empty());
+    }
+
+    #[test]
+    fn token_positions() {
+        let mut inverted_index = InvertedIndex::new();
+
+        inverted_index.add_token_with_positions("123", "foo", 2., vec![0, 3]);
+        inverted_index.add_token("456", "foo", 1.);
+
+        assert_eq!(inverted_index.get_positions("123", "foo"), &[0, 3]);
+        assert_eq!(inverted_index.get_term_frequency("123", "foo"), 2.);
+        assert!(inverted_index.get_positions("456", "foo").is_empty());
+        assert!(inverted_index.get_positions("789", "foo").is_empty());
+        assert!(inverted_index.get_positions("123", "bar").is_empty());
+
+        let json = serde_json::to_string(&inverted_index).unwrap();
+        assert_eq!(
+            json,
+            r#"{"root":{"docs":{},"df":0,"f":{"docs":{},"df":0,"o":{"docs":{},"df":0,"o":{"docs":{"123":{"tf":2.0,"positions":[0,3]},"456":{"tf":1.0}},"df":2}}}}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<InvertedIndex>(&json).unwrap(),
+            inverted_index
+        );
+
+        inverted_index.add_token_with_positions("789", "foo", 2., vec![5, 1, 3, 1]);
+        assert_eq!(inverted_index.get_positions("789", "foo"), &[1, 3, 5]);
+
+        let unsorted = json.replace("[0,3]", "[3,0,3]");
+        let deserialized: InvertedIndex = serde_json::from_str(&unsorted).unwrap();
+        assert_eq!(deserialized.get_positions("123", "foo"), &[0, 3]);
+    }
+
+    #[test]
+    fn merging_indexes() {
+        let mut inverted_index = InvertedIndex::new();
+        inverted_index.add_token("123", "foo", 1.);
+        inverted_index.add_token("123", "bar", 2.);
+
+        let mut other = InvertedIndex::new();
+        other.add_token("456", "foo", 3.);
+        other.add_token("456", "food", 4.);
+        other.add_token("123", "bar", 5.);
+
+        inverted_index.merge(other);
+        assert_eq!(
+            inverted_index.get_docs("foo").unwrap(),
+            btreemap! {
+                "123".into() => 1.,
+                "456".into() => 3.,
+            }
+        );
+        assert_eq!(inverted_index.get_doc_frequency("foo"), 2);
+        assert_eq!(inverted_index.get_term_frequency("456", "food"), 4.);
+        assert_eq!(inverted_index.get_doc_frequency("food"), 1);
+        assert_eq!(inverted_index.get_term_frequency("123", "bar"), 5.);
+        assert_eq!(inverted_index.get_doc_frequency("bar"), 1);
+    }
+
+    #[test]
+    fn split_by_prefix() {
+        let mut inverted_index = InvertedIndex::new();
+        inverted_index.add_token("123", "a", 1.);
+        inverted_index.add_token("123", "foo", 1.);
+        inverted_index.add_token_with_positions("456", "food", 2., vec![0, 3]);
+        inverted_index.add_token("456", "bar", 3.);
+
+        let indexes = inverted_index.split_by_prefix(2);
+        assert_eq!(
+            indexes.keys().collect::<Vec<_>>(),
+            ["a", "ba", "fo"].iter().collect::<Vec<_>>()
+        );
+        assert_eq!(indexes["fo"].expand_token("f"), ["foo", "food"]);
+        assert_eq!(indexes["fo"].get_positions("456", "food"), [0, 3]);
+        assert!(!indexes["a"].has_token("foo"));
+
+        let mut merged = InvertedIndex::new();
+        for index in indexes.into_values() {
+            merged.merge(index);
+        }
+        assert_eq!(merged, inverted_index);
+
+        let indexes = inverted_index.split_by_prefix(0);
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[""], inverted_index);
+    }
+
     #[test]
     fn get_term_frequency() {
         let mut inverted_index = InvertedIndex::new();
@@ -6,7 +6,10 @@ pub mod common;
 
 use crate::Pipeline;
 
-pub trait Language {
+/// A language, which provides the tokenizer and pipeline used to index and search text.
+///
+/// Languages must be `Send` and `Sync`, as an [`Index`](crate::Index) is.
+pub trait Language: Send + Sync {
     /// The name of the language in English
     fn name(&self) -> String;
 
@@ -72,6 +75,17 @@ macro_rules! impl_language {
             }
         }
 
+        /// Returns the cargo feature which includes the [`Language`] with the given English name,
+        /// whether or not it is enabled.
+        pub(crate) fn feature_for_name(name: &str) -> Option<&'static str> {
+            match name {
+                $(
+                    stringify!($name) => Some(stringify!($code)),
+                )+
+                _ => None,
+            }
+        }
+
         $(
             $(#[$cfgs])?
             mod $code;
@@ -36,15 +36,35 @@ extern crate maplit;
 /// The version of elasticlunr.js this library was designed for.
 pub const ELASTICLUNR_VERSION: &str = "0.9.5";
 
+pub mod binary;
 pub mod config;
+pub mod document;
 pub mod document_store;
+pub mod highlight;
 pub mod inverted_index;
 pub mod lang;
+pub mod lunr;
 pub mod pipeline;
+pub mod query;
+pub mod search;
+pub mod shard;
+pub mod stats;
+
+mod error;
 
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io;
+
+use serde::Serialize;
+use serde_json::Value;
 
+use config::{SearchOptions, SearchOptionsField};
+pub use document::Document;
 use document_store::DocumentStore;
+#[cfg(feature = "derive")]
+pub use elasticlunr_derive::Document;
+pub use error::Error;
 use inverted_index::InvertedIndex;
 use lang::English;
 pub use lang::Language;
@@ -54,7 +74,7 @@ pub use pipeline::Pipeline;
 ///
 /// Used with [`IndexBuilder::add_field_with_tokenizer`] to override the
 /// [`Language`]'s default tokenizer for one field.
-pub type TokenizerFn = Box<dyn Fn(&str) -> Vec<String>>;
+pub type TokenizerFn = Box<dyn Fn(&str) -> Vec<String> + Send + Sync>;
 
 type Tokenizer = Option<TokenizerFn>;
 
@@ -72,8 +92,10 @@ type Tokenizer = Option<TokenizerFn>;
 /// ```
 pub struct IndexBuilder {
     save: bool,
+    store_positions: bool,
     fields: Vec<String>,
     field_tokenizers: Vec<Tokenizer>,
+    field_options: Vec<SearchOptionsField>,
     ref_field: String,
     pipeline: Option<Pipeline>,
     language: Box<dyn Language>,
@@ -83,8 +105,10 @@ impl Default for IndexBuilder {
     fn default() -> Self {
         IndexBuilder {
             save: true,
+            store_positions: false,
             fields: Vec::new(),
             field_tokenizers: Vec::new(),
+            field_options: Vec::new(),
             ref_field: "id".into(),
             pipeline: None,
             language: Box::new(English::new()),
@@ -110,19 +134,31 @@ impl IndexBuilder {
         self
     }
 
+    /// Set whether or not the positions of each token in a document should be saved in the
+    /// `Index`, which is needed for [`Index::search_phrase`]. Defaults to `false`.
+    ///
+    /// Positions make the serialized index larger. elasticlunr.js ignores them, so the index is
+    /// still compatible with it.
+    pub fn store_positions(mut self, store: bool) -> Self {
+        self.store_positions = store;
+        self
+    }
+
     /// Add a document field to the `Index`.
     ///
     /// # Panics
     ///
     /// Panics if a field with the name already exists.
-    pub fn add_field(mut self, field: &str) -> Self {
-        let field = field.into();
-        if self.fields.contains(&field) {
-            panic!("Duplicate fields in index: {}", field);
-        }
-        self.fields.push(field);
-        self.field_tokenizers.push(None);
-        self
+    pub fn add_field(self, field: &str) -> Self {
+        self.try_add_field(field)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Add a document field to the `Index`, returning an error if a field with the name already
+    /// exists.
+    pub fn try_add_field(mut self, field: &str) -> Result<Self, Error> {
+        self.push_field(field, None, Default::default())?;
+        Ok(self)
     }
 
     /// Add a document field to the `Index`, with a custom tokenizer for that field.
@@ -130,14 +166,43 @@ impl IndexBuilder {
     /// # Panics
     ///
     /// Panics if a field with the name already exists.
-    pub fn add_field_with_tokenizer(mut self, field: &str, tokenizer: TokenizerFn) -> Self {
-        let field = field.into();
-        if self.fields.contains(&field) {
-            panic!("Duplicate fields in index: {}", field);
-        }
-        self.fields.push(field);
-        self.field_tokenizers.push(Some(tokenizer));
-        self
+    pub fn add_field_with_tokenizer(self, field: &str, tokenizer: TokenizerFn) -> Self {
+        self.try_add_field_with_tokenizer(field, tokenizer)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Add a document field to the `Index`, with a custom tokenizer for that field. Returns an
+    /// error if a field with the name already exists.
+    pub fn try_add_field_with_tokenizer(
+        mut self,
+        field: &str,
+        tokenizer: TokenizerFn,
+    ) -> Result<Self, Error> {
+        self.push_field(field, Some(tokenizer), Default::default())?;
+        Ok(self)
+    }
+
+    /// Add a document field to the `Index`, with the options used to search it.
+    ///
+    /// The options are used by [`Index::search_options`] and are not serialized with the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a field with the name already exists.
+    pub fn add_field_with_options(self, field: &str, options: SearchOptionsField) -> Self {
+        self.try_add_field_with_options(field, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Add a document field to the `Index`, with the options used to search it. Returns an
+    /// error if a field with the name already exists.
+    pub fn try_add_field_with_options(
+        mut self,
+        field: &str,
+        options: SearchOptionsField,
+    ) -> Result<Self, Error> {
+        self.push_field(field, None, options)?;
+        Ok(self)
     }
 
     /// Add the document fields to the `Index`.
@@ -145,15 +210,50 @@ impl IndexBuilder {
     /// # Panics
     ///
     /// Panics if two fields have the same name.
-    pub fn add_fields<I>(mut self, fields: I) -> Self
+    pub fn add_fields<I>(self, fields: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.try_add_fields(fields)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Add the document fields to the `Index`, returning an error if two fields have the same
+    /// name.
+    pub fn try_add_fields<I>(mut self, fields: I) -> Result<Self, Error>
     where
         I: IntoIterator,
         I::Item: AsRef<str>,
     {
         for field in fields {
-            self = self.add_field(field.as_ref())
+            self.push_field(field.as_ref(), None, Default::default())?;
         }
-        self
+        Ok(self)
+    }
+
+    fn push_field(
+        &mut self,
+        field: &str,
+        tokenizer: Tokenizer,
+        options: SearchOptionsField,
+    ) -> Result<(), Error> {
+        if self.fields.iter().any(|f| f == field) {
+            return Err(Error::DuplicateField(field.into()));
+        }
+        self.fields.push(field.into());
+        self.field_tokenizers.push(tokenizer);
+        self.field_options.push(options);
+        Ok(())
+    }
+
+    /// Add the fields of a [`Document`] type to the `Index`, and use its reference field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a field with the same name as one of the document's fields already exists.
+    pub fn add_document_fields<D: Document>(self) -> Self {
+        D::add_fields(self)
     }
 
     /// Set the key used to store the document reference field.
@@ -162,12 +262,22 @@ impl IndexBuilder {
         self
     }
 
+    /// Build an `Index` from this builder, returning an error if no fields were added.
+    pub fn try_build(self) -> Result<Index, Error> {
+        if self.fields.is_empty() {
+            return Err(Error::NoFields);
+        }
+        Ok(self.build())
+    }
+
     /// Build an `Index` from this builder.
     pub fn build(self) -> Index {
         let IndexBuilder {
             save,
+            store_positions,
             fields,
             field_tokenizers,
+            field_options,
             ref_field,
             pipeline,
             language,
@@ -184,8 +294,10 @@ impl IndexBuilder {
             index,
             fields,
             field_tokenizers,
+            field_options,
             ref_field,
             document_store: DocumentStore::new(save),
+            store_positions,
             pipeline,
             version: crate::ELASTICLUNR_VERSION,
             lang: language,
@@ -194,66 +306,119 @@ impl IndexBuilder {
 }
 
 /// An elasticlunr search index.
+///
+/// An `Index` can be deserialized from any serde format, but [`from_json`](#method.from_json)
+/// and [`from_reader`](#method.from_reader) report errors with an [`Error`] instead of a
+/// message. Custom tokenizers added with [`IndexBuilder::add_field_with_tokenizer`] and search
+/// options added with [`IndexBuilder::add_field_with_options`] are not serialized, so a
+/// deserialized index uses the [`Language`]'s tokenizer and the default options for every field.
+///
+/// An `Index` is `Send` and `Sync`, so it can be built on one thread and shared between threads
+/// which search it, for example with an `Arc`. Its [`Language`], pipeline functions and
+/// tokenizers are required to be `Send` and `Sync` for this.
 #[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", try_from = "IndexRepr")]
 pub struct Index {
     fields: Vec<String>,
     #[serde(skip)]
     field_tokenizers: Vec<Tokenizer>,
+    #[serde(skip)]
+    field_options: Vec<SearchOptionsField>,
     pipeline: Pipeline,
     #[serde(rename = "ref")]
     ref_field: String,
+    // Deserialization goes through `IndexRepr`, but serde would still require `'de: 'static`.
+    #[serde(skip_deserializing)]
     version: &'static str,
     index: BTreeMap<String, InvertedIndex>,
     document_store: DocumentStore,
-    #[serde(with = "ser_lang")]
+    #[serde(skip_serializing_if = "is_false")]
+    store_positions: bool,
+    #[serde(serialize_with = "ser_lang")]
     lang: Box<dyn Language>,
 }
 
-mod ser_lang {
-    use crate::Language;
-    use serde::de;
-    use serde::{Deserializer, Serializer};
-    use std::fmt;
+// Fails to compile if a field of `Index` or `IndexBuilder` stops being `Send` or `Sync`.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Index>();
+    assert_send_sync::<IndexBuilder>();
+};
 
-    // `serde(with = ...)` hands us a reference to the field itself, which is a
-    // `Box<dyn Language>`, so the extra indirection is not ours to remove.
-    #[allow(clippy::borrowed_box)]
-    pub fn serialize<S>(lang: &Box<dyn Language>, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_str(&lang.name())
-    }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Box<dyn Language>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        deserializer.deserialize_str(LanguageVisitor)
-    }
+fn is_false(value: &bool) -> bool {
+    !value
+}
 
-    struct LanguageVisitor;
+// `serialize_with` hands us a reference to the field itself, which is a `Box<dyn Language>`, so
+// the extra indirection is not ours to remove.
+#[allow(clippy::borrowed_box)]
+fn ser_lang<S>(lang: &Box<dyn Language>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&lang.name())
+}
 
-    impl<'de> de::Visitor<'de> for LanguageVisitor {
-        type Value = Box<dyn Language>;
+/// The serialized form of an `Index`, before the language and pipeline have been looked up.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexRepr {
+    fields: Vec<String>,
+    pipeline: Vec<String>,
+    #[serde(rename = "ref")]
+    ref_field: String,
+    index: BTreeMap<String, InvertedIndex>,
+    document_store: DocumentStore,
+    #[serde(default)]
+    store_positions: bool,
+    lang: String,
+}
 
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a capitalized language name")
-        }
+impl TryFrom<IndexRepr> for Index {
+    type Error = Error;
 
-        fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            match crate::lang::from_name(v) {
-                Some(l) => Ok(l),
-                None => Err(E::custom(format!("Unknown language name: {}", v))),
+    fn try_from(repr: IndexRepr) -> Result<Self, Error> {
+        let lang = lang::from_name(&repr.lang).ok_or_else(|| {
+            match lang::feature_for_name(&repr.lang) {
+                Some(feature) => Error::LanguageNotEnabled {
+                    name: repr.lang.clone(),
+                    feature,
+                },
+                None => Error::UnknownLanguage(repr.lang.clone()),
             }
-        }
+        })?;
+        let pipeline = Pipeline::from_names(&repr.pipeline)?;
+
+        Ok(Index {
+            field_tokenizers: repr.fields.iter().map(|_| None).collect(),
+            field_options: vec![Default::default(); repr.fields.len()],
+            fields: repr.fields,
+            pipeline,
+            ref_field: repr.ref_field,
+            version: crate::ELASTICLUNR_VERSION,
+            index: repr.index,
+            document_store: repr.document_store,
+            store_positions: repr.store_positions,
+            lang,
+        })
     }
 }
 
+/// A document which has been tokenized and processed by the pipeline, but not yet added to an
+/// index.
+struct AnalyzedDoc {
+    doc_ref: String,
+    doc: BTreeMap<String, String>,
+    fields: Vec<AnalyzedField>,
+}
+
+struct AnalyzedField {
+    /// The index of the field in `Index::fields`.
+    field: usize,
+    length: usize,
+    token_positions: BTreeMap<String, Vec<usize>>,
+}
+
 impl Index {
     /// Create a new index with the provided fields.
     ///
@@ -303,23 +468,97 @@ impl Index {
     /// *NOTE: The elements of `data` should be provided in the same order as
     /// the fields used to create the index.*
     ///
+    /// If the index already contains a document with the same reference, it is replaced as if by
+    /// [`update_doc`](#method.update_doc).
+    ///
+    /// If `data` has fewer elements than the index has fields, the remaining fields are left out
+    /// of the document, so they match nothing when searching. Use
+    /// [`try_add_doc`](#method.try_add_doc) to get an error instead.
+    ///
     /// # Example
     /// ```
     /// # use elasticlunr::Index;
     /// let mut index = Index::new(&["title", "body"]);
     /// index.add_doc("1", &["this is a title", "this is body text"]);
+    /// index.add_doc("2", &["this document has no body"]);
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` has more elements than the index has fields.
     pub fn add_doc<I>(&mut self, doc_ref: &str, data: I)
     where
         I: IntoIterator,
         I::Item: AsRef<str>,
     {
+        let data: Vec<_> = data.into_iter().collect();
+        if data.len() > self.fields.len() {
+            panic!("{}", self.too_many_values(data.len()));
+        }
+
+        let doc = self.analyze_doc(doc_ref, data);
+        self.insert_doc(doc);
+    }
+
+    /// Add the data from many documents to the index, as if by calling
+    /// [`add_doc`](#method.add_doc) for each of them in order, but tokenize and process them
+    /// with the pipeline on multiple threads with [rayon](https://docs.rs/rayon). The resulting
+    /// index is identical to one built sequentially.
+    ///
+    /// Requires the `rayon` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_docs_par(vec![
+    ///     ("1", ["this is a title", "this is body text"]),
+    ///     ("2", ["another title", "more body text"]),
+    /// ]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a document has more values than the index has fields, before any of the
+    /// documents are added.
+    #[cfg(feature = "rayon")]
+    pub fn add_docs_par<I, R, D>(&mut self, docs: I)
+    where
+        I: IntoIterator<Item = (R, D)>,
+        R: AsRef<str> + Send,
+        D: IntoIterator + Send,
+        D::Item: AsRef<str>,
+    {
+        use rayon::prelude::*;
+
+        let docs: Vec<_> = docs.into_iter().collect();
+        let docs: Vec<_> = docs
+            .into_par_iter()
+            .map(|(doc_ref, data)| {
+                let data: Vec<_> = data.into_iter().collect();
+                if data.len() > self.fields.len() {
+                    panic!("{}", self.too_many_values(data.len()));
+                }
+                self.analyze_doc(doc_ref.as_ref(), data)
+            })
+            .collect();
+
+        // Adding the documents in order keeps the last of any documents with the same reference,
+        // like `add_doc`.
+        for doc in docs {
+            self.insert_doc(doc);
+        }
+    }
+
+    /// Tokenizes the values of a document and runs them through the pipeline.
+    fn analyze_doc<T: AsRef<str>>(&self, doc_ref: &str, data: Vec<T>) -> AnalyzedDoc {
         let mut doc = BTreeMap::new();
         doc.insert(self.ref_field.clone(), doc_ref.into());
 
+        let mut fields = Vec::new();
         for (i, value) in data.into_iter().enumerate() {
             let field = &self.fields[i];
-            let tokenizer = self.field_tokenizers[i].as_ref();
+            let tokenizer = self.field_tokenizers.get(i).and_then(Option::as_ref);
             doc.insert(field.clone(), value.as_ref().to_string());
 
             if field == &self.ref_field {
@@ -332,47 +571,483 @@ impl Index {
                 self.lang.tokenize(value.as_ref())
             };
 
-            let tokens = self.pipeline.run(raw_tokens);
+            let tokens = self.pipeline.run_with_positions(raw_tokens);
+            let length = tokens.len();
+
+            let mut token_positions = BTreeMap::new();
+            for (position, token) in tokens {
+                token_positions
+                    .entry(token)
+                    .or_insert_with(Vec::new)
+                    .push(position);
+            }
+
+            fields.push(AnalyzedField {
+                field: i,
+                length,
+                token_positions,
+            });
+        }
+
+        AnalyzedDoc {
+            doc_ref: doc_ref.into(),
+            doc,
+            fields,
+        }
+    }
+
+    /// Adds a document returned by `analyze_doc` to the index, replacing any document with the
+    /// same reference.
+    fn insert_doc(&mut self, analyzed: AnalyzedDoc) {
+        let AnalyzedDoc {
+            doc_ref,
+            doc,
+            fields,
+        } = analyzed;
+        self.remove_doc(&doc_ref);
 
+        for AnalyzedField {
+            field,
+            length,
+            token_positions,
+        } in fields
+        {
+            let field = &self.fields[field];
             self.document_store
-                .add_field_length(doc_ref, field, tokens.len());
+                .add_field_length(&doc_ref, field, length);
+
+            let index = self
+                .index
+                .get_mut(field)
+                .unwrap_or_else(|| panic!("InvertedIndex does not exist for field {}", field));
+            for (token, positions) in token_positions {
+                let freq = (positions.len() as f64).sqrt();
+
+                if self.store_positions {
+                    index.add_token_with_positions(&doc_ref, &token, freq, positions);
+                } else {
+                    index.add_token(&doc_ref, &token, freq);
+                }
+            }
+        }
+
+        self.document_store.add_doc(&doc_ref, doc);
+    }
+
+    /// Add the data from a document to the index, like [`add_doc`](#method.add_doc), but return
+    /// an error instead of ignoring or panicking on a mismatch between `data` and the fields of
+    /// the index. The index is not modified if an error is returned.
+    ///
+    /// Unlike `add_doc`, which leaves out the fields after the last element of `data`, this
+    /// returns [`Error::MissingField`] with the first field which has no value.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::{Error, Index};
+    /// let mut index = Index::new(&["title", "body"]);
+    /// assert!(index.try_add_doc("1", &["this is a title", "this is body text"]).is_ok());
+    /// assert!(matches!(
+    ///     index.try_add_doc("2", &["this is a title"]),
+    ///     Err(Error::MissingField(field)) if field == "body"
+    /// ));
+    /// ```
+    pub fn try_add_doc<I>(&mut self, doc_ref: &str, data: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let data: Vec<_> = data.into_iter().collect();
+        if data.len() > self.fields.len() {
+            return Err(self.too_many_values(data.len()));
+        }
+        if let Some(field) = self.fields.get(data.len()) {
+            return Err(Error::MissingField(field.clone()));
+        }
+
+        self.add_doc(doc_ref, data);
+        Ok(())
+    }
 
-            let mut token_freq = BTreeMap::new();
-            for token in tokens {
-                *token_freq.entry(token).or_insert(0u64) += 1;
+    fn too_many_values(&self, found: usize) -> Error {
+        Error::TooManyValues {
+            expected: self.fields.len(),
+            found,
+        }
+    }
+
+    /// Add a document to the index from a value which serializes to a map, such as a struct
+    /// deriving `Serialize`.
+    ///
+    /// The reference and the data of each field are looked up by name. Numbers and booleans are
+    /// converted to text, `null` is treated as an empty string, and the elements of arrays are
+    /// joined with spaces. Other entries of the map are ignored.
+    ///
+    /// Returns an error if the reference or any field is missing, if the value of a field can't
+    /// be converted to text, or if the document doesn't serialize to a map. The index is not
+    /// modified if an error is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # #[macro_use] extern crate serde_derive;
+    /// # use elasticlunr::Index;
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     id: u32,
+    ///     title: String,
+    ///     body: String,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut index = Index::new(&["title", "body", "tags"]);
+    /// index
+    ///     .add_doc_serde(&Page {
+    ///         id: 1,
+    ///         title: "this is a title".into(),
+    ///         body: "this is body text".into(),
+    ///         tags: vec!["foo".into(), "bar".into()],
+    ///     })
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn add_doc_serde<T>(&mut self, doc: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let doc = match serde_json::to_value(doc)? {
+            Value::Object(doc) => doc,
+            _ => return Err(Error::InvalidDocument),
+        };
+
+        let field_text = |field: &str| match doc.get(field) {
+            Some(value) => value_to_text(field, value),
+            None => Err(Error::MissingField(field.into())),
+        };
+
+        let doc_ref = field_text(&self.ref_field)?;
+        let data = self
+            .fields
+            .iter()
+            .map(|field| field_text(field))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.add_doc(&doc_ref, data);
+        Ok(())
+    }
+
+    /// Add a [`Document`] to the index.
+    ///
+    /// The index should have been created with the document's fields, using
+    /// [`IndexBuilder::add_document_fields`]. Like [`add_doc`](#method.add_doc), any existing
+    /// document with the same reference is replaced.
+    pub fn add_document<D: Document + ?Sized>(&mut self, doc: &D) {
+        let doc_ref = doc.doc_ref();
+        self.add_doc(&doc_ref, doc.field_values());
+
+        if self.document_store.is_stored() {
+            if let Some(stored) = self.document_store.docs.get_mut(&doc_ref) {
+                for (field, value) in doc.stored_values() {
+                    stored.insert(field.into(), value);
+                }
             }
+        }
+    }
+
+    /// Replace a document in the index with new data, removing the tokens of the previous version.
+    ///
+    /// Returns `false` if the index did not contain a document with that reference, in which case
+    /// the document is added.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["this is a title", "this is body text"]);
+    /// assert!(index.update_doc("1", &["this is a new title", "this is new body text"]));
+    /// ```
+    pub fn update_doc<I>(&mut self, doc_ref: &str, data: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        // `add_doc` removes the previous version.
+        let existed = self.document_store.has_doc(doc_ref);
+        self.add_doc(doc_ref, data);
+        existed
+    }
 
-            for (token, count) in &token_freq {
-                let freq = (*count as f64).sqrt();
+    /// Remove a document from the index.
+    ///
+    /// Every posting of the document is removed from each field's index, along with its field
+    /// lengths and its entry in the document store. Returns `false` if the index does not
+    /// contain a document with that reference.
+    ///
+    /// If the index saves documents, the tokens of the document are found by processing the
+    /// saved document again, so only those tokens are visited. Otherwise, or if these aren't the
+    /// tokens the document was indexed with, every token of the index is visited.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["this is a title", "this is body text"]);
+    /// assert!(index.remove_doc("1"));
+    /// assert!(!index.remove_doc("1"));
+    /// ```
+    pub fn remove_doc(&mut self, doc_ref: &str) -> bool {
+        if !self.document_store.has_doc(doc_ref) {
+            return false;
+        }
 
-                self.index
-                    .get_mut(field)
-                    .unwrap_or_else(|| panic!("InvertedIndex does not exist for field {}", field))
-                    .add_token(doc_ref, token, freq);
+        match self.saved_doc_tokens(doc_ref) {
+            Some(fields) => {
+                for AnalyzedField {
+                    field,
+                    token_positions,
+                    ..
+                } in fields
+                {
+                    if let Some(index) = self.index.get_mut(&self.fields[field]) {
+                        index.remove_doc_tokens(doc_ref, token_positions.keys());
+                    }
+                }
+            }
+            None => {
+                for index in self.index.values_mut() {
+                    index.remove_doc(doc_ref);
+                }
             }
         }
+        self.document_store.remove_doc(doc_ref);
+        true
+    }
+
+    /// Finds the tokens of the saved document `doc_ref` by processing it again. Returns `None` if
+    /// documents aren't saved, or if the tokens aren't the ones the document was indexed with,
+    /// for example because it was indexed with a custom tokenizer which a deserialized index
+    /// doesn't have.
+    ///
+    /// The tokens are the indexed ones if the document has a posting with the same term
+    /// frequency for each of them, and they add up to the field length of the document.
+    fn saved_doc_tokens(&self, doc_ref: &str) -> Option<Vec<AnalyzedField>> {
+        if !self.document_store.save {
+            return None;
+        }
+        let doc = self.document_store.docs.get(doc_ref)?;
+        let data: Vec<&str> = self
+            .fields
+            .iter()
+            .map(|field| doc.get(field).map_or("", String::as_str))
+            .collect();
+
+        let fields = self.analyze_doc(doc_ref, data).fields;
+        let indexed = fields.iter().all(|analyzed| {
+            let field = &self.fields[analyzed.field];
+            let index = match self.index.get(field) {
+                Some(index) => index,
+                None => return false,
+            };
+            analyzed.length == self.document_store.get_field_length(doc_ref, field)
+                && analyzed.token_positions.iter().all(|(token, positions)| {
+                    index.get_term_frequency(doc_ref, token) == (positions.len() as f64).sqrt()
+                })
+        });
+        if indexed {
+            Some(fields)
+        } else {
+            None
+        }
+    }
+
+    /// Moves every document of `other` into this index, so that indexes built separately, for
+    /// example in parallel, can be searched or serialized as one.
+    ///
+    /// Both indexes need to have the same fields, reference field, language, pipeline and
+    /// settings for saving documents and positions. The custom tokenizers and search options
+    /// of the fields of this index are kept. An error is returned if the indexes are different,
+    /// or if they both contain a document with the same reference, in which case this index is
+    /// not modified.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["this is a title", "this is body text"]);
+    ///
+    /// let mut other = Index::new(&["title", "body"]);
+    /// other.add_doc("2", &["another title", "more body text"]);
+    ///
+    /// index.merge(other).unwrap();
+    /// assert_eq!(index.search("title", &Default::default()).len(), 2);
+    /// ```
+    pub fn merge(&mut self, other: Index) -> Result<(), Error> {
+        let incompatible = if self.fields != other.fields {
+            Some("fields")
+        } else if self.ref_field != other.ref_field {
+            Some("reference fields")
+        } else if self.lang.code() != other.lang.code() {
+            Some("languages")
+        } else if self.pipeline.names() != other.pipeline.names() {
+            Some("pipelines")
+        } else if self.document_store.save != other.document_store.save {
+            Some("settings for saving documents")
+        } else if self.store_positions != other.store_positions {
+            Some("settings for storing positions")
+        } else {
+            None
+        };
+        if let Some(setting) = incompatible {
+            return Err(Error::IncompatibleIndexes(setting));
+        }
 
-        self.document_store.add_doc(doc_ref, doc);
+        if let Some(doc_ref) = other
+            .document_store
+            .docs
+            .keys()
+            .find(|doc_ref| self.document_store.has_doc(doc_ref))
+        {
+            return Err(Error::DuplicateDocument(doc_ref.clone()));
+        }
+
+        for (field, index) in other.index {
+            self.index.entry(field).or_default().merge(index);
+        }
+        self.document_store.merge(other.document_store);
+        Ok(())
+    }
+
+    /// Returns the search options for this index, with the options of each field which were
+    /// given to [`IndexBuilder::add_field_with_options`].
+    ///
+    /// Every field of the index is included, so that the options can be passed to elasticlunr.js
+    /// or [`search`](#method.search) to search all fields.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::{IndexBuilder, config::SearchOptionsField};
+    /// let index = IndexBuilder::new()
+    ///     .add_field_with_options(
+    ///         "title",
+    ///         SearchOptionsField {
+    ///             boost: Some(2),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .add_field("body")
+    ///     .build();
+    /// assert_eq!(
+    ///     serde_json::to_string(&index.search_options()).unwrap(),
+    ///     r#"{"bool":"OR","expand":false,"fields":{"body":{},"title":{"boost":2}}}"#
+    /// );
+    /// ```
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            fields: Some(
+                self.fields
+                    .iter()
+                    .cloned()
+                    .zip(self.field_options.iter().cloned())
+                    .collect(),
+            ),
+            ..Default::default()
+        }
     }
 
     pub fn get_fields(&self) -> &[String] {
         &self.fields
     }
 
+    /// Deserialize an index from a string of JSON.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["this is a title", "this is body text"]);
+    ///
+    /// let json = index.to_json();
+    /// let index = Index::from_json(&json).unwrap();
+    /// assert_eq!(index.to_json(), json);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let repr: IndexRepr = serde_json::from_str(json)?;
+        Index::try_from(repr)
+    }
+
+    /// Deserialize an index from a reader of JSON, such as a `File`.
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<Self, Error> {
+        let repr: IndexRepr = serde_json::from_reader(reader)?;
+        Index::try_from(repr)
+    }
+
     /// Returns the index, serialized to pretty-printed JSON.
+    ///
+    /// The whole index is built in memory, so use
+    /// [`write_json_pretty`](#method.write_json_pretty) for writing large indexes to a file.
     pub fn to_json_pretty(&self) -> String {
         serde_json::to_string_pretty(&self).unwrap()
     }
 
     /// Returns the index, serialized to JSON.
+    ///
+    /// The whole index is built in memory, so use [`write_json`](#method.write_json) for
+    /// writing large indexes to a file.
     pub fn to_json(&self) -> String {
         serde_json::to_string(&self).unwrap()
     }
+
+    /// Serializes the index to JSON, writing it to `writer` as it is serialized instead of
+    /// building it in memory first. Errors from the writer are returned as [`Error::Io`].
+    ///
+    /// The writer is not buffered, so wrap writers like `File` in a `BufWriter`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use elasticlunr::Index;
+    /// # use std::{fs::File, io::{BufWriter, Write}};
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["this is a title", "this is body text"]);
+    ///
+    /// let mut writer = BufWriter::new(File::create("out.json")?);
+    /// index.write_json(&mut writer)?;
+    /// writer.flush()?;
+    /// # Ok::<(), elasticlunr::Error>(())
+    /// ```
+    pub fn write_json<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, &self)?;
+        Ok(())
+    }
+
+    /// Serializes the index to pretty-printed JSON, writing it to `writer` like
+    /// [`write_json`](#method.write_json).
+    pub fn write_json_pretty<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+}
+
+/// Converts the value of a field in a serialized document to the text to index.
+fn value_to_text(field: &str, value: &Value) -> Result<String, Error> {
+    match value {
+        Value::Null => Ok(String::new()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.clone()),
+        Value::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| value_to_text(field, value))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(values.join(" "))
+        }
+        Value::Object(_) => Err(Error::InvalidFieldValue(field.into())),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn add_field_to_builder() {
@@ -433,6 +1108,554 @@ mod tests {
         assert_eq!(idx.index["body"].get_docs("appl").unwrap()["1"], 1.);
     }
 
+    #[test]
+    fn removing_document_from_index() {
+        let mut idx = Index::new(&["title", "body"]);
+
+        idx.add_doc("1", &["apple", "banana cherry"]);
+        idx.add_doc("2", &["apple pie", "cherry"]);
+        assert!(idx.remove_doc("1"));
+
+        assert_eq!(idx.document_store.len(), 1);
+        assert!(!idx.document_store.has_doc("1"));
+        assert_eq!(idx.document_store.get_field_length("1", "body"), 0);
+        assert!(!idx.document_store.doc_info.contains_key("1"));
+        assert_eq!(idx.index["title"].get_doc_frequency("appl"), 1);
+        assert_eq!(idx.index["body"].get_doc_frequency("cherri"), 1);
+        assert!(!idx.index["body"].has_token("b"));
+        assert_eq!(idx.index["title"].get_term_frequency("1", "appl"), 0.);
+    }
+
+    #[test]
+    fn updating_document_in_index() {
+        let mut idx = Index::new(&["title", "body"]);
+
+        idx.add_doc("1", &["apple apple", "banana"]);
+        idx.add_doc("2", &["apple", "cherry"]);
+        assert!(idx.update_doc("1", &["apple", "cherry cherry"]));
+
+        assert_eq!(idx.document_store.len(), 2);
+        assert_eq!(idx.index["title"].get_term_frequency("1", "appl"), 1.);
+        assert_eq!(idx.index["title"].get_doc_frequency("appl"), 2);
+        assert!(!idx.index["body"].has_token("banana"));
+        assert_eq!(idx.index["body"].get_doc_frequency("cherri"), 2);
+        assert_eq!(idx.document_store.get_field_length("1", "body"), 2);
+        assert_eq!(
+            idx.document_store.get_doc("1").unwrap()["body"],
+            "cherry cherry"
+        );
+
+        assert!(!idx.update_doc("3", &["durian", ""]));
+        assert_eq!(idx.document_store.len(), 3);
+    }
+
+    #[test]
+    fn adding_duplicate_document_replaces_it() {
+        let mut idx = Index::new(&["title", "body"]);
+
+        idx.add_doc("1", &["apple apple apple", "banana"]);
+        idx.add_doc("1", &["apple", "cherry"]);
+
+        let mut expected = Index::new(&["title", "body"]);
+        expected.add_doc("1", &["apple", "cherry"]);
+        assert_eq!(idx.to_json(), expected.to_json());
+    }
+
+    #[test]
+    fn removing_document_from_deserialized_index() {
+        let mut idx = Index::new(&["title", "body"]);
+        idx.add_doc("1", &["apple", "banana cherry"]);
+        idx.add_doc("2", &["apple pie", "cherry"]);
+        idx.add_doc("3", &["banana", "cherry pie"]);
+
+        let mut deserialized = Index::from_json(&idx.to_json()).unwrap();
+        assert!(idx.remove_doc("1"));
+        assert!(deserialized.remove_doc("1"));
+        assert_eq!(idx.to_json(), deserialized.to_json());
+
+        idx.add_doc("3", &["durian", "pie"]);
+        deserialized.add_doc("3", &["durian", "pie"]);
+        assert_eq!(idx.to_json(), deserialized.to_json());
+        assert!(!idx.index["title"].has_token("banana"));
+    }
+
+    #[test]
+    fn removing_document_without_its_tokens() {
+        fn split_commas(text: &str) -> Vec<String> {
+            text.split(',').map(str::to_string).collect()
+        }
+
+        // A deserialized index doesn't have the custom tokenizer, so the saved document isn't
+        // processed into the tokens it was indexed with.
+        let mut idx = IndexBuilder::new()
+            .add_field_with_tokenizer("tags", Box::new(split_commas))
+            .build();
+        idx.add_doc("1", &["rust,web apps"]);
+        idx.add_doc("2", &["rust"]);
+        let mut deserialized = Index::from_json(&idx.to_json()).unwrap();
+        assert!(idx.remove_doc("1"));
+        assert!(deserialized.remove_doc("1"));
+        assert!(!deserialized.index["tags"].has_token("web app"));
+        assert_eq!(idx.to_json(), deserialized.to_json());
+
+        let mut idx = IndexBuilder::new()
+            .save_docs(false)
+            .add_fields(&["title", "body"])
+            .build();
+        idx.add_doc("1", &["apple", "banana cherry"]);
+        idx.add_doc("2", &["apple pie", "cherry"]);
+        assert!(idx.remove_doc("1"));
+
+        let mut expected = IndexBuilder::new()
+            .save_docs(false)
+            .add_fields(&["title", "body"])
+            .build();
+        expected.add_doc("2", &["apple pie", "cherry"]);
+        assert_eq!(idx.to_json(), expected.to_json());
+    }
+
+    #[test]
+    fn removing_nonexistant_document_from_index() {
+        let mut idx = Index::new(&["title", "body"]);
+
+        idx.add_doc("1", &["apple", "banana"]);
+        assert!(!idx.remove_doc("2"));
+        assert_eq!(idx.document_store.len(), 1);
+        assert_eq!(idx.index["title"].get_doc_frequency("appl"), 1);
+    }
+
+    #[test]
+    fn adding_document_to_deserialized_index() {
+        let mut idx: Index = serde_json::from_str(
+            r#"{
+                "fields": ["body"],
+                "pipeline": ["trimmer", "stopWordFilter", "stemmer"],
+                "ref": "id",
+                "version": "0.9.5",
+                "index": { "body": { "root": { "docs": {}, "df": 0 } } },
+                "documentStore": { "save": true, "docs": {}, "docInfo": {}, "length": 0 },
+                "lang": "English"
+            }"#,
+        )
+        .unwrap();
+        idx.add_doc("1", &["The apples are falling"]);
+
+        let mut expected = Index::new(&["body"]);
+        expected.add_doc("1", &["The apples are falling"]);
+        assert_eq!(idx.to_json(), expected.to_json());
+    }
+
+    #[test]
+    fn adding_serialized_document() {
+        let mut idx = Index::new(&["title", "body", "tags", "year"]);
+        idx.add_doc_serde(&json!({
+            "id": 1,
+            "title": "apple",
+            "body": null,
+            "tags": ["banana", ["cherry"]],
+            "year": 2024,
+            "ignored": { "foo": "bar" },
+        }))
+        .unwrap();
+
+        let mut expected = Index::new(&["title", "body", "tags", "year"]);
+        expected.add_doc("1", &["apple", "", "banana cherry", "2024"]);
+        assert_eq!(idx.to_json(), expected.to_json());
+    }
+
+    #[test]
+    fn adding_serialized_document_with_missing_field() {
+        let mut idx = Index::new(&["title", "body"]);
+
+        match idx.add_doc_serde(&json!({ "id": "1", "title": "apple" })) {
+            Err(Error::MissingField(field)) => assert_eq!(field, "body"),
+            _ => panic!("expected a missing field error"),
+        }
+        match idx.add_doc_serde(&json!({ "title": "apple", "body": "banana" })) {
+            Err(Error::MissingField(field)) => assert_eq!(field, "id"),
+            _ => panic!("expected a missing field error"),
+        }
+        assert!(idx.document_store.is_empty());
+    }
+
+    #[test]
+    fn adding_invalid_serialized_document() {
+        let mut idx = Index::new(&["title"]);
+
+        match idx.add_doc_serde(&json!({ "id": "1", "title": { "text": "apple" } })) {
+            Err(Error::InvalidFieldValue(field)) => assert_eq!(field, "title"),
+            _ => panic!("expected an invalid field value error"),
+        }
+        assert!(matches!(
+            idx.add_doc_serde(&["1", "apple"]),
+            Err(Error::InvalidDocument)
+        ));
+        assert!(idx.document_store.is_empty());
+    }
+
+    #[test]
+    fn deserializing_index() {
+        let mut idx = Index::new(&["title", "body"]);
+        idx.add_doc("1", &["apple", "banana cherry"]);
+        let json = idx.to_json();
+
+        assert_eq!(Index::from_json(&json).unwrap().to_json(), json);
+        assert_eq!(Index::from_reader(json.as_bytes()).unwrap().to_json(), json);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let idx: Index = serde_json::from_value(value).unwrap();
+        assert_eq!(idx.to_json(), json);
+    }
+
+    #[test]
+    fn writing_json() {
+        let mut idx = Index::new(&["title", "body"]);
+        idx.add_doc("1", &["apple", "banana cherry"]);
+
+        let mut json = Vec::new();
+        idx.write_json(&mut json).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), idx.to_json());
+
+        let mut json = Vec::new();
+        idx.write_json_pretty(&mut json).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), idx.to_json_pretty());
+
+        struct FullWriter;
+        impl io::Write for FullWriter {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        match idx.write_json(FullWriter) {
+            Err(Error::Io(err)) => assert_eq!(err.to_string(), "disk full"),
+            _ => panic!("expected an I/O error"),
+        }
+    }
+
+    #[test]
+    fn deserializing_unknown_language() {
+        let json = Index::new(&["body"])
+            .to_json()
+            .replace("English", "Klingon");
+        match Index::from_json(&json) {
+            Err(Error::UnknownLanguage(name)) => assert_eq!(name, "Klingon"),
+            _ => panic!("expected an unknown language error"),
+        }
+    }
+
+    #[cfg(not(feature = "de"))]
+    #[test]
+    fn deserializing_disabled_language() {
+        let json = Index::new(&["body"]).to_json().replace("English", "German");
+        match Index::from_json(&json) {
+            Err(Error::LanguageNotEnabled { name, feature }) => {
+                assert_eq!(name, "German");
+                assert_eq!(feature, "de");
+            }
+            _ => panic!("expected a language not enabled error"),
+        }
+    }
+
+    #[test]
+    fn deserializing_unknown_pipeline_function() {
+        let json = Index::new(&["body"])
+            .to_json()
+            .replace("\"stemmer\"", "\"lowercase\"");
+        match Index::from_json(&json) {
+            Err(Error::UnknownPipelineFunction(name)) => assert_eq!(name, "lowercase"),
+            _ => panic!("expected an unknown pipeline function error"),
+        }
+    }
+
+    #[test]
+    fn storing_positions() {
+        let mut index = Index::new(&["body"]);
+        index.add_doc("1", &["the cat sat on the cat"]);
+        let json = serde_json::to_value(&index).unwrap();
+        assert!(json.get("storePositions").is_none());
+        assert_eq!(
+            json["index"]["body"]["root"]["c"]["a"]["t"]["docs"]["1"],
+            json!({"tf": 2f64.sqrt()})
+        );
+
+        let mut index = IndexBuilder::new()
+            .store_positions(true)
+            .add_field("body")
+            .build();
+        index.add_doc("1", &["the cat sat on the cat"]);
+        assert_eq!(index.index["body"].get_positions("1", "cat"), &[1, 5]);
+        assert_eq!(index.index["body"].get_positions("1", "sat"), &[2]);
+
+        let json = serde_json::to_value(&index).unwrap();
+        assert_eq!(json["storePositions"], json!(true));
+        let index: Index = serde_json::from_value(json).unwrap();
+        assert!(index.store_positions);
+        assert_eq!(index.index["body"].get_positions("1", "cat"), &[1, 5]);
+    }
+
+    #[test]
+    fn merging_indexes() {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc("1", &["Oracle released its latest database", "Oracle 12g"]);
+        index.add_doc("2", &["Apple sells apples", "apple profit database"]);
+
+        let mut other = Index::new(&["title", "body"]);
+        other.add_doc(
+            "3",
+            &["Oracle released its profit report", "database profit"],
+        );
+
+        let mut expected = Index::new(&["title", "body"]);
+        expected.add_doc("1", &["Oracle released its latest database", "Oracle 12g"]);
+        expected.add_doc("2", &["Apple sells apples", "apple profit database"]);
+        expected.add_doc(
+            "3",
+            &["Oracle released its profit report", "database profit"],
+        );
+
+        index.merge(other).unwrap();
+        assert_eq!(index.to_json(), expected.to_json());
+        assert_eq!(index.index["title"].get_doc_frequency("oracl"), 2);
+
+        // The tokens of the merged documents are kept for removing them.
+        assert!(index.remove_doc("3"));
+        assert!(expected.remove_doc("3"));
+        assert_eq!(index.to_json(), expected.to_json());
+        assert!(!index.index["title"].has_token("report"));
+    }
+
+    #[test]
+    fn merging_incompatible_indexes() {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc("1", &["title", "body"]);
+
+        let check = |index: &mut Index, other: Index, expected: &str| match index.merge(other) {
+            Err(Error::IncompatibleIndexes(setting)) => assert_eq!(setting, expected),
+            result => panic!("unexpected result: {:?}", result),
+        };
+        check(&mut index, Index::new(&["title"]), "fields");
+        check(
+            &mut index,
+            IndexBuilder::new()
+                .add_fields(&["title", "body"])
+                .set_ref("url")
+                .build(),
+            "reference fields",
+        );
+        #[cfg(feature = "ar")]
+        check(
+            &mut index,
+            IndexBuilder::with_language(Box::new(lang::Arabic::new()))
+                .add_fields(&["title", "body"])
+                .build(),
+            "languages",
+        );
+        let mut json = serde_json::to_value(Index::new(&["title", "body"])).unwrap();
+        json["pipeline"] = json!(["trimmer"]);
+        check(
+            &mut index,
+            serde_json::from_value(json).unwrap(),
+            "pipelines",
+        );
+        check(
+            &mut index,
+            IndexBuilder::new()
+                .save_docs(false)
+                .add_fields(&["title", "body"])
+                .build(),
+            "settings for saving documents",
+        );
+        check(
+            &mut index,
+            IndexBuilder::new()
+                .store_positions(true)
+                .add_fields(&["title", "body"])
+                .build(),
+            "settings for storing positions",
+        );
+
+        let json = index.to_json();
+        let mut other = Index::new(&["title", "body"]);
+        other.add_doc("2", &["another", "document"]);
+        other.add_doc("1", &["conflicting", "document"]);
+        assert!(matches!(
+            index.merge(other),
+            Err(Error::DuplicateDocument(doc_ref)) if doc_ref == "1"
+        ));
+        assert_eq!(index.to_json(), json);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn adding_documents_in_parallel() {
+        let text = include_str!("../tests/data/en.in.txt");
+        let docs: Vec<_> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| ((i % 40).to_string(), [line, text]))
+            .collect();
+
+        let build = || {
+            IndexBuilder::new()
+                .store_positions(true)
+                .add_fields(&["title", "body"])
+                .build()
+        };
+        let mut sequential = build();
+        for (doc_ref, data) in &docs {
+            sequential.add_doc(doc_ref, data);
+        }
+        let mut parallel = build();
+        parallel.add_docs_par(docs);
+
+        assert_eq!(parallel.to_json(), sequential.to_json());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn adding_documents_in_parallel_with_too_many_values() {
+        let mut index = Index::new(&["title", "body"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            index.add_docs_par(vec![
+                ("1", vec!["title", "body"]),
+                ("2", vec!["title", "body", "extra"]),
+            ]);
+        }));
+        assert!(result.is_err());
+        assert!(index.document_store.is_empty());
+    }
+
+    #[test]
+    fn sharing_index_between_threads() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let builder = IndexBuilder::new()
+            .add_field("title")
+            .add_field_with_tokenizer("tags", Box::new(lang::tokenize_whitespace));
+        let index = thread::spawn(move || {
+            let mut index = builder.build();
+            index.add_doc("1", &["Searching in threads", "rust threads"]);
+            index.add_doc("2", &["Searching alone", "rust"]);
+            index
+        })
+        .join()
+        .unwrap();
+
+        let index = Arc::new(index);
+        let handles: Vec<_> = ["threads", "rust", "alone"]
+            .iter()
+            .map(|query| {
+                let index = Arc::clone(&index);
+                thread::spawn(move || index.search(query, &Default::default()).len())
+            })
+            .collect();
+        let counts: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(counts, [1, 2, 1]);
+    }
+
+    #[test]
+    fn try_building_index() {
+        let idx = IndexBuilder::new()
+            .try_add_field("title")
+            .and_then(|builder| builder.try_add_fields(&["body", "tags"]))
+            .and_then(|builder| builder.try_build())
+            .unwrap();
+        assert_eq!(idx.get_fields(), &["title", "body", "tags"]);
+
+        let err = IndexBuilder::new()
+            .try_add_fields(&["title", "body"])
+            .and_then(|builder| {
+                builder.try_add_field_with_tokenizer("title", Box::new(lang::tokenize_whitespace))
+            })
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::DuplicateField(field) if field == "title"));
+
+        assert!(matches!(
+            IndexBuilder::new().try_build(),
+            Err(Error::NoFields)
+        ));
+    }
+
+    #[test]
+    fn try_adding_document() {
+        let mut idx = Index::new(&["title", "body"]);
+
+        idx.try_add_doc("1", &["apple", "banana"]).unwrap();
+        assert!(matches!(
+            idx.try_add_doc("1", &["apple", "banana", "cherry"]),
+            Err(Error::TooManyValues {
+                expected: 2,
+                found: 3
+            })
+        ));
+        assert!(matches!(
+            idx.try_add_doc("1", &["cherry"]),
+            Err(Error::MissingField(field)) if field == "body"
+        ));
+
+        let mut expected = Index::new(&["title", "body"]);
+        expected.add_doc("1", &["apple", "banana"]);
+        assert_eq!(idx.to_json(), expected.to_json());
+    }
+
+    #[test]
+    fn adding_document_with_fewer_values() {
+        let mut idx = Index::new(&["title", "body"]);
+        idx.add_doc("1", &["apple"]);
+
+        assert!(idx.document_store.has_doc("1"));
+        assert_eq!(idx.index["title"].get_doc_frequency("appl"), 1);
+        assert_eq!(idx.document_store.get_field_length("1", "title"), 1);
+        assert!(!idx.document_store.doc_info["1"].contains_key("body"));
+        assert!(!idx.document_store.docs["1"].contains_key("body"));
+
+        assert!(matches!(
+            idx.try_add_doc("2", &["apple"]),
+            Err(Error::MissingField(field)) if field == "body"
+        ));
+        assert!(!idx.document_store.has_doc("2"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many values")]
+    fn adding_document_with_too_many_values_panics() {
+        let mut idx = Index::new(&["title"]);
+        idx.add_doc("1", &["apple", "banana"]);
+    }
+
+    #[test]
+    fn field_search_options() {
+        let title = SearchOptionsField {
+            boost: Some(3),
+            ..Default::default()
+        };
+        let idx = IndexBuilder::new()
+            .add_field_with_options("title", title)
+            .add_field("body")
+            .build();
+
+        let options = idx.search_options();
+        assert_eq!(
+            options.fields,
+            Some(btreemap! {
+                "title".into() => title,
+                "body".into() => SearchOptionsField::default(),
+            })
+        );
+        assert!(options.validate(&idx).is_ok());
+
+        let idx = Index::from_json(&idx.to_json()).unwrap();
+        assert_eq!(
+            idx.search_options().fields.unwrap()["title"],
+            SearchOptionsField::default()
+        );
+    }
+
     #[test]
     #[should_panic]
     fn creating_index_with_identical_fields_panics() {
@@ -0,0 +1,379 @@
+//! Exports an [`Index`] in the serialized index format of [lunr.js](https://lunrjs.com) 2.x, so
+//! that the same indexer can serve both elasticlunr.js and lunr.js search clients. Most users only
+//! need [`Index::to_lunr_json`].
+//!
+//! lunr.js scores documents with BM25 when the index is built, rather than when searching like
+//! elasticlunr.js, so the exported index contains a vector of term scores for each field of each
+//! document. The boost of each field from
+//! [`IndexBuilder::add_field_with_options`](crate::IndexBuilder::add_field_with_options) is
+//! applied to these scores, like a field boost given to the lunr.js builder.
+//!
+//! Like the lunr.js builder, the exported pipeline is the search pipeline, which only has the
+//! stemmer of the language. lunr.js can only load the index if the stemmer is registered, either
+//! by lunr.js itself for English or by the
+//! [lunr-languages](https://github.com/MihaiValentin/lunr-languages) plugin for the language.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::highlight::token_ranges;
+use crate::{Error, Index};
+
+/// The version of lunr.js the exported indexes are written for.
+pub const LUNR_VERSION: &str = "2.3.9";
+
+// The BM25 parameters of the lunr.js builder.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+impl Index {
+    /// Returns the index in the serialized format of lunr.js 2.x, which can be loaded with
+    /// `lunr.Index.load`.
+    ///
+    /// If the index was built with [`store_positions`](crate::IndexBuilder::store_positions) and
+    /// saves documents, each posting includes the `position` metadata of the token, as if
+    /// `position` had been added to the `metadataWhitelist` of the lunr.js builder.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["Apples", "Apples are a fruit"]);
+    ///
+    /// let json: serde_json::Value = serde_json::from_str(&index.to_lunr_json()).unwrap();
+    /// assert_eq!(json["version"], "2.3.9");
+    /// assert_eq!(json["invertedIndex"][0][0], "appl");
+    /// assert_eq!(json["fieldVectors"][0][0], "title/1");
+    /// ```
+    pub fn to_lunr_json(&self) -> String {
+        serde_json::to_string(&self.to_lunr()).unwrap()
+    }
+
+    /// Serializes the index in the format of lunr.js 2.x, writing it to `writer` like
+    /// [`write_json`](#method.write_json).
+    pub fn write_lunr_json<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, &self.to_lunr())?;
+        Ok(())
+    }
+
+    fn to_lunr(&self) -> LunrIndex<'_> {
+        // The reference is not indexed, so it isn't a lunr.js field.
+        let fields: Vec<(&str, f64)> = self
+            .fields
+            .iter()
+            .zip(&self.field_options)
+            .filter(|(field, _)| **field != self.ref_field)
+            .map(|(field, options)| {
+                let boost = match options.boost {
+                    Some(boost) if boost > 0 => f64::from(boost),
+                    _ => 1.,
+                };
+                (field.as_str(), boost)
+            })
+            .collect();
+        let store = &self.document_store;
+        let doc_count = store.len() as f64;
+
+        // The postings of each term, by field and then by document.
+        let mut terms: BTreeMap<String, Vec<FieldPostings<'_>>> = BTreeMap::new();
+        for (i, (field, _)) in fields.iter().enumerate() {
+            if let Some(index) = self.index.get(*field) {
+                index.for_each_token(|token, doc_ref, term_freq, positions| {
+                    let postings = terms
+                        .entry(token.to_string())
+                        .or_insert_with(|| vec![BTreeMap::new(); fields.len()]);
+                    postings[i].insert(doc_ref, (term_freq, positions));
+                });
+            }
+        }
+        // lunr.js compares terms as UTF-16 strings when it loads them.
+        let mut terms: Vec<_> = terms.into_iter().collect();
+        terms.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+        let field_length = |doc_ref: &str, field: &str| {
+            store
+                .doc_info
+                .get(doc_ref)
+                .and_then(|info| info.get(field))
+                .map_or(0, |&length| length) as f64
+        };
+        let average_lengths: Vec<f64> = fields
+            .iter()
+            .map(|(field, _)| {
+                let total: f64 = store
+                    .docs
+                    .keys()
+                    .map(|doc_ref| field_length(doc_ref, field))
+                    .sum();
+                total / doc_count
+            })
+            .collect();
+
+        let with_positions = self.store_positions && store.save;
+        let mut token_positions = BTreeMap::new();
+        let mut vectors: BTreeMap<(&str, usize), Vec<(usize, f64)>> = BTreeMap::new();
+        let mut inverted_index = Vec::with_capacity(terms.len());
+        for (term_index, (term, postings)) in terms.into_iter().enumerate() {
+            let docs_with_term: usize = postings.iter().map(BTreeMap::len).sum();
+            let docs_with_term = docs_with_term as f64;
+            let x = (doc_count - docs_with_term + 0.5) / (docs_with_term + 0.5);
+            let idf = (1. + x.abs()).ln();
+
+            let mut posting_fields = Vec::with_capacity(fields.len());
+            for (i, docs) in postings.into_iter().enumerate() {
+                let (field, boost) = fields[i];
+                let mut metadata = BTreeMap::new();
+                for (doc_ref, (term_freq, positions)) in docs {
+                    // elasticlunr.js stores the square root of the token count.
+                    let tf = (term_freq * term_freq).round();
+                    let length_ratio = field_length(doc_ref, field) / average_lengths[i];
+                    let score = idf * ((K1 + 1.) * tf) / (K1 * (1. - B + B * length_ratio) + tf);
+                    let score = score * boost;
+                    vectors
+                        .entry((doc_ref, i))
+                        .or_default()
+                        .push((term_index, (score * 1000.).round() / 1000.));
+
+                    let position = if with_positions {
+                        let ranges = token_positions
+                            .entry((doc_ref, i))
+                            .or_insert_with(|| self.lunr_token_positions(doc_ref, field));
+                        let position = positions
+                            .iter()
+                            .filter_map(|&p| ranges.get(p).copied().flatten())
+                            .collect();
+                        Some(position)
+                    } else {
+                        None
+                    };
+                    metadata.insert(doc_ref, Metadata { position });
+                }
+                posting_fields.push((field, metadata));
+            }
+
+            inverted_index.push((
+                term,
+                Posting {
+                    index: term_index,
+                    fields: posting_fields,
+                },
+            ));
+        }
+
+        let mut field_vectors = Vec::with_capacity(store.len() * fields.len());
+        for doc_ref in store.docs.keys() {
+            for (i, (field, _)) in fields.iter().enumerate() {
+                let vector = vectors.remove(&(doc_ref.as_str(), i)).unwrap_or_default();
+                field_vectors.push((format!("{}/{}", field, doc_ref), FieldVector(vector)));
+            }
+        }
+
+        LunrIndex {
+            version: LUNR_VERSION,
+            fields: fields.iter().map(|(field, _)| *field).collect(),
+            field_vectors,
+            inverted_index,
+            // lunr.js only runs the stemmer on query terms, so its builder serializes a search
+            // pipeline with just the stemmer.
+            pipeline: self
+                .pipeline
+                .names()
+                .into_iter()
+                .filter(|name| name.starts_with("stemmer"))
+                .collect(),
+        }
+    }
+
+    /// Finds where each token of `field` of the saved document is, as a start and a length in
+    /// UTF-16 code units like the positions of lunr.js.
+    fn lunr_token_positions(&self, doc_ref: &str, field: &str) -> Vec<Option<[usize; 2]>> {
+        let text = match self
+            .document_store
+            .docs
+            .get(doc_ref)
+            .and_then(|doc| doc.get(field))
+        {
+            Some(text) => text,
+            None => return Vec::new(),
+        };
+        let ranges = token_ranges(text, &self.raw_query_tokens(field, text));
+
+        // The ranges are in order, so the text only needs to be counted once.
+        let mut byte = 0;
+        let mut utf16 = 0;
+        let mut utf16_offset = |to: usize| {
+            utf16 += text[byte..to].encode_utf16().count();
+            byte = to;
+            utf16
+        };
+        ranges
+            .into_iter()
+            .map(|range| {
+                let range = range?;
+                let start = utf16_offset(range.start);
+                let end = utf16_offset(range.end);
+                Some([start, end - start])
+            })
+            .collect()
+    }
+}
+
+/// The term frequency and positions of a term in each document containing it in a field.
+type FieldPostings<'a> = BTreeMap<&'a str, (f64, &'a [usize])>;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LunrIndex<'a> {
+    version: &'static str,
+    fields: Vec<&'a str>,
+    field_vectors: Vec<(String, FieldVector)>,
+    inverted_index: Vec<(String, Posting<'a>)>,
+    pipeline: Vec<String>,
+}
+
+/// The score of each term in a field of a document, sorted by term index. lunr.js serializes it
+/// as a flat array of term indexes and scores.
+struct FieldVector(Vec<(usize, f64)>);
+
+impl Serialize for FieldVector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len() * 2))?;
+        for (term_index, score) in &self.0 {
+            seq.serialize_element(term_index)?;
+            seq.serialize_element(score)?;
+        }
+        seq.end()
+    }
+}
+
+/// The documents containing a term in each field, along with the index of the term in the field
+/// vectors.
+struct Posting<'a> {
+    index: usize,
+    fields: Vec<(&'a str, BTreeMap<&'a str, Metadata>)>,
+}
+
+impl Serialize for Posting<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len() + 1))?;
+        map.serialize_entry("_index", &self.index)?;
+        for (field, docs) in &self.fields {
+            map.serialize_entry(field, docs)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<Vec<[usize; 2]>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SearchOptionsField;
+    use crate::IndexBuilder;
+    use serde_json::{json, Value};
+
+    fn lunr_json(index: &Index) -> Value {
+        serde_json::from_str(&index.to_lunr_json()).unwrap()
+    }
+
+    #[test]
+    fn exporting_lunr_index() {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc("1", &["Apple", "apple banana"]);
+        index.add_doc("2", &["Banana", "cherry"]);
+
+        // idf(appl) = idf(banana) = ln(1.2) and idf(cherri) = ln(2). The average length of the
+        // title is 1 and of the body is 1.5.
+        assert_eq!(
+            lunr_json(&index),
+            json!({
+                "version": "2.3.9",
+                "fields": ["title", "body"],
+                "fieldVectors": [
+                    ["title/1", [0, 0.182]],
+                    ["body/1", [0, 0.16, 1, 0.16]],
+                    ["title/2", [1, 0.182]],
+                    ["body/2", [2, 0.803]],
+                ],
+                "invertedIndex": [
+                    ["appl", {"_index": 0, "title": {"1": {}}, "body": {"1": {}}}],
+                    ["banana", {"_index": 1, "title": {"2": {}}, "body": {"1": {}}}],
+                    ["cherri", {"_index": 2, "title": {}, "body": {"2": {}}}],
+                ],
+                "pipeline": ["stemmer"],
+            })
+        );
+
+        let mut json = Vec::new();
+        index.write_lunr_json(&mut json).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), index.to_lunr_json());
+    }
+
+    #[test]
+    fn exporting_lunr_index_with_boost() {
+        let mut index = IndexBuilder::new()
+            .add_field_with_options(
+                "title",
+                SearchOptionsField {
+                    boost: Some(2),
+                    ..Default::default()
+                },
+            )
+            .add_field("body")
+            .build();
+        index.add_doc("1", &["Apple", "apple banana"]);
+        index.add_doc("2", &["Banana"]);
+
+        let json = lunr_json(&index);
+        assert_eq!(json["fields"], json!(["title", "body"]));
+        assert_eq!(
+            json["fieldVectors"],
+            json!([
+                ["title/1", [0, 0.365]],
+                ["body/1", [0, 0.129, 1, 0.129]],
+                ["title/2", [1, 0.365]],
+                ["body/2", []],
+            ])
+        );
+    }
+
+    #[test]
+    fn exporting_lunr_positions() {
+        let mut index = IndexBuilder::new()
+            .store_positions(true)
+            .add_field("body")
+            .build();
+        index.add_doc("1", &["Café apples and apples"]);
+
+        let json = lunr_json(&index);
+        assert_eq!(
+            json["invertedIndex"],
+            json!([
+                ["appl", {"_index": 0, "body": {"1": {"position": [[5, 6], [16, 6]]}}}],
+                ["caf", {"_index": 1, "body": {"1": {"position": [[0, 4]]}}}],
+            ])
+        );
+    }
+
+    #[test]
+    fn lunr_term_order() {
+        let mut index = Index::new(&["body"]);
+        index.add_doc("1", &["body"]);
+        let body = index.index.get_mut("body").unwrap();
+        body.add_token("1", "\u{ff41}", 1.);
+        body.add_token("1", "\u{1d41a}", 1.);
+
+        // U+1D41A is encoded as surrogates in UTF-16, which are less than U+FF41.
+        let json = lunr_json(&index);
+        assert_eq!(json["invertedIndex"][0][0], "bodi");
+        assert_eq!(json["invertedIndex"][1][0], "\u{1d41a}");
+        assert_eq!(json["invertedIndex"][2][0], "\u{ff41}");
+    }
+}
@@ -1,9 +1,18 @@
 //! Defines the pipeline which processes text for inclusion in the index. Most users do not need
 //! to use this module directly.
 
+use std::collections::BTreeMap;
+
+use serde::de::{self, Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 
-pub trait PipelineFn {
+use crate::lang::{self, Language};
+use crate::Error;
+
+/// A function which processes each token of a [`Pipeline`].
+///
+/// Pipeline functions must be `Send` and `Sync`, as an [`Index`](crate::Index) is.
+pub trait PipelineFn: Send + Sync {
     fn name(&self) -> String;
 
     fn filter(&self, token: String) -> Option<String>;
@@ -22,10 +31,35 @@ impl PipelineFn for FnWrapper {
     }
 }
 
+/// Returns the built-in [`PipelineFn`] with the given name, as serialized in an index, if the
+/// language it belongs to is supported. Returns `None` if not supported.
+///
+/// The functions of each language are named like their lunr-languages counterparts, which have
+/// the language code as a suffix (e.g. `stemmer-de`). The English functions have no suffix.
+pub fn from_name(name: &str) -> Option<Box<dyn PipelineFn>> {
+    let lang = lang::from_code(language_code(name))?;
+    find_fn(lang.as_ref(), name)
+}
+
+fn language_code(name: &str) -> &str {
+    name.rsplit_once('-').map_or("en", |(_, code)| code)
+}
+
+fn find_fn(lang: &dyn Language, name: &str) -> Option<Box<dyn PipelineFn>> {
+    take_fn(&mut lang.make_pipeline().queue, name)
+}
+
+/// Removes the function with the given name from `queue`.
+fn take_fn(queue: &mut Vec<Box<dyn PipelineFn>>, name: &str) -> Option<Box<dyn PipelineFn>> {
+    let i = queue.iter().position(|func| func.name() == name)?;
+    Some(queue.remove(i))
+}
+
 /// A sequence of `PipelineFn`s which are run on tokens to prepare them for searching.
-#[derive(Deserialize)]
+///
+/// A `Pipeline` is serialized as the list of its functions' names. When deserialized, each name
+/// is looked up with [`from_name`], and unknown names are an error.
 pub struct Pipeline {
-    #[serde(skip_deserializing)]
     pub queue: Vec<Box<dyn PipelineFn>>,
 }
 
@@ -42,24 +76,143 @@ impl Serialize for Pipeline {
     }
 }
 
+impl<'de> Deserialize<'de> for Pipeline {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        Pipeline::from_names(&names).map_err(de::Error::custom)
+    }
+}
+
 impl Pipeline {
+    /// Create a `Pipeline` of the built-in functions with the given names, as if by [`from_name`].
+    pub fn from_names<I>(names: I) -> Result<Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        // Some languages are expensive to create, so only create each language and its pipeline
+        // once, and take all of its functions from that pipeline.
+        let mut pipelines = BTreeMap::new();
+        let mut queue = Vec::new();
+        for name in names {
+            let name = name.as_ref();
+            let code = language_code(name);
+            let functions = pipelines.entry(code.to_string()).or_insert_with(|| {
+                lang::from_code(code).map_or_else(Vec::new, |lang| lang.make_pipeline().queue)
+            });
+
+            // A function which is in the pipeline more than once needs another copy.
+            let func = take_fn(functions, name)
+                .or_else(|| from_name(name))
+                .ok_or_else(|| Error::UnknownPipelineFunction(name.into()))?;
+            queue.push(func);
+        }
+
+        Ok(Pipeline { queue })
+    }
+
+    /// The names of the functions in the pipeline, as they are serialized.
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.queue.iter().map(|func| func.name()).collect()
+    }
+
     /// Run the Pipeline against the given vector of tokens. The returned vector may be shorter
     /// than the input if a pipeline function returns `None` for a token.
     pub fn run(&self, tokens: Vec<String>) -> Vec<String> {
-        let mut ret = vec![];
-        for token in tokens {
-            let mut token = Some(token);
-            for func in &self.queue {
-                if let Some(t) = token {
-                    token = func.filter(t);
-                } else {
-                    break;
-                }
-            }
+        tokens
+            .into_iter()
+            .filter_map(|token| self.filter(token))
+            .collect()
+    }
+
+    /// Run the Pipeline against the given vector of tokens, like [`run`](#method.run), but keep
+    /// the position of each remaining token in the input.
+    pub fn run_with_positions(&self, tokens: Vec<String>) -> Vec<(usize, String)> {
+        tokens
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, token)| Some((position, self.filter(token)?)))
+            .collect()
+    }
+
+    fn filter(&self, token: String) -> Option<String> {
+        let mut token = Some(token);
+        for func in &self.queue {
             if let Some(t) = token {
-                ret.push(t);
+                token = func.filter(t);
+            } else {
+                break;
             }
         }
-        ret
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(pipeline: &Pipeline) -> Vec<String> {
+        pipeline.queue.iter().map(|func| func.name()).collect()
+    }
+
+    #[test]
+    fn functions_from_name() {
+        assert_eq!(from_name("stemmer").unwrap().name(), "stemmer");
+        assert_eq!(
+            from_name("stopWordFilter").unwrap().filter("the".into()),
+            None
+        );
+        assert!(from_name("stemmer-xx").is_none());
+        assert!(from_name("lowercase").is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        for lang in lang::languages() {
+            let pipeline = lang.make_pipeline();
+            let json = serde_json::to_string(&pipeline).unwrap();
+            let deserialized: Pipeline = serde_json::from_str(&json).unwrap();
+            assert_eq!(names(&deserialized), names(&pipeline));
+        }
+    }
+
+    #[test]
+    fn deserialized_pipeline_runs() {
+        let pipeline: Pipeline =
+            serde_json::from_str(r#"["trimmer", "stopWordFilter", "stemmer"]"#).unwrap();
+        assert_eq!(
+            pipeline.run(vec!["the".into(), "(running)".into()]),
+            vec!["run".to_string()]
+        );
+    }
+
+    #[test]
+    fn repeated_function_names() {
+        let pipeline = Pipeline::from_names(&["stemmer", "trimmer", "stemmer"]).unwrap();
+        assert_eq!(names(&pipeline), ["stemmer", "trimmer", "stemmer"]);
+    }
+
+    #[test]
+    fn run_with_positions() {
+        let pipeline = lang::English::new().make_pipeline();
+        let tokens = ["state", "of", "the", "art", "(running)"];
+        assert_eq!(
+            pipeline.run_with_positions(tokens.iter().map(|t| t.to_string()).collect()),
+            vec![(0, "state".into()), (3, "art".into()), (4, "run".into())]
+        );
+    }
+
+    #[test]
+    fn unknown_function_name() {
+        let err = serde_json::from_str::<Pipeline>(r#"["trimmer", "lowercase"]"#)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("Unknown pipeline function: lowercase"));
     }
 }
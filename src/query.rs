@@ -0,0 +1,626 @@
+//! Parses and executes search queries with a small query language.
+//!
+//! A query is a list of clauses separated by whitespace. Each clause is a word or a quoted
+//! phrase, optionally preceded by a field name and a colon to only search that field, and
+//! followed by `^` and a boost:
+//!
+//! |Query             |Matches documents which                                         |
+//! |------------------|----------------------------------------------------------------|
+//! |`rust`            |contain `rust`                                                  |
+//! |`title:rust`      |contain `rust` in the `title` field                             |
+//! |`"exact phrase"`  |contain the phrase `exact phrase`                               |
+//! |`+async`          |must contain `async`                                            |
+//! |`-deprecated`     |must not contain `deprecated`                                   |
+//! |`docs^2`          |contain `docs`, which counts twice as much towards their score  |
+//!
+//! Documents need to match at least one clause, and every clause starting with `+`. Phrases
+//! need an index which [stores positions](crate::IndexBuilder::store_positions).
+//!
+//! # Example
+//!
+//! ```
+//! # use elasticlunr::{IndexBuilder, config::SearchOptions, query::Query};
+//! let mut index = IndexBuilder::new()
+//!     .store_positions(true)
+//!     .add_fields(&["title", "body"])
+//!     .build();
+//! index.add_doc("1", &["Async Rust", "Writing async code with futures"]);
+//! index.add_doc("2", &["Old Rust", "Deprecated async code with tasks"]);
+//! index.add_doc("3", &["Rust docs", "How to write documentation"]);
+//!
+//! let query: Query = r#"title:rust +async -deprecated "async code""#.parse().unwrap();
+//! let results = index.search_query(&query, &SearchOptions::default()).unwrap();
+//! assert_eq!(results.len(), 1);
+//! assert_eq!(results[0].doc_ref, "1");
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error;
+use std::fmt;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::{CharIndices, FromStr};
+
+use crate::config::{SearchBool, SearchOptions};
+use crate::search::{self, SearchResult};
+use crate::{Error, Index};
+
+/// A parsed query. See the [module documentation](self) for the syntax.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+/// A single word or phrase of a [`Query`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause {
+    pub occur: Occur,
+    /// The field to search, or `None` to search every field.
+    pub field: Option<String>,
+    pub term: Term,
+    /// The number the score of the clause is multiplied by.
+    pub boost: f64,
+    /// The byte range of the clause in the query string.
+    pub span: Range<usize>,
+}
+
+/// Whether documents must, may or must not match a [`Clause`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Occur {
+    /// No prefix: documents may match the clause.
+    Should,
+    /// `+`: documents must match the clause.
+    Must,
+    /// `-`: documents must not match the clause.
+    MustNot,
+}
+
+/// The text searched for by a [`Clause`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Term {
+    Word(String),
+    Phrase(String),
+}
+
+/// An error in the syntax of a query.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The byte range of the error in the query string.
+    pub span: Range<usize>,
+}
+
+/// The kinds of [`ParseError`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A `+`, `-` or field name is not followed by a word or phrase.
+    MissingTerm,
+    /// A field name is empty.
+    MissingField,
+    /// A phrase has no closing quote.
+    UnterminatedPhrase,
+    /// A phrase has no words.
+    EmptyPhrase,
+    /// A `^` is not followed by a positive number.
+    InvalidBoost,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.kind {
+            ParseErrorKind::MissingTerm => "expected a word or phrase",
+            ParseErrorKind::MissingField => "expected a field name before `:`",
+            ParseErrorKind::UnterminatedPhrase => "phrase is missing a closing `\"`",
+            ParseErrorKind::EmptyPhrase => "phrase is empty",
+            ParseErrorKind::InvalidBoost => "expected a positive number after `^`",
+        };
+        write!(f, "{} at {}..{}", message, self.span.start, self.span.end)
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(query: &str) -> Result<Self, ParseError> {
+        Query::parse(query)
+    }
+}
+
+impl Query {
+    /// Parses a query string. See the [module documentation](self) for the syntax.
+    pub fn parse(query: &str) -> Result<Self, ParseError> {
+        Parser {
+            input: query,
+            chars: query.char_indices().peekable(),
+        }
+        .parse()
+    }
+
+    /// Converts the query to a query string and options for elasticlunr.js's `Index.search`, if
+    /// it can be expressed that way. The converted query matches the same documents, though
+    /// elasticlunr.js may score them differently.
+    ///
+    /// This is only possible if the query has no phrases, `-` clauses or boosts, every clause
+    /// searches the same fields, and either no clause or every clause starts with `+`. Clauses
+    /// starting with `+` must also search a single field, because elasticlunr.js requires every
+    /// token to be found in the same field.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elasticlunr::{config::SearchBool, query::Query};
+    /// let query = Query::parse("+title:async +title:rust").unwrap();
+    /// let (query, options) = query.to_search_options().unwrap();
+    /// assert_eq!(query, "async rust");
+    /// assert_eq!(options.bool, SearchBool::And);
+    /// assert!(options.fields.unwrap().contains_key("title"));
+    ///
+    /// assert!(Query::parse("async -rust").unwrap().to_search_options().is_none());
+    /// ```
+    pub fn to_search_options(&self) -> Option<(String, SearchOptions)> {
+        let first = self.clauses.first()?;
+        let mut words = Vec::new();
+        for clause in &self.clauses {
+            if clause.occur != first.occur || clause.field != first.field || clause.boost != 1. {
+                return None;
+            }
+            match &clause.term {
+                Term::Word(word) => words.push(word.as_str()),
+                Term::Phrase(_) => return None,
+            }
+        }
+
+        let bool = match first.occur {
+            Occur::Should => SearchBool::Or,
+            Occur::Must if first.field.is_some() || words.len() == 1 => SearchBool::And,
+            _ => return None,
+        };
+        let mut options = SearchOptions {
+            bool,
+            ..Default::default()
+        };
+        if let Some(field) = &first.field {
+            let mut fields = BTreeMap::new();
+            fields.insert(field.clone(), Default::default());
+            options.fields = Some(fields);
+        }
+        Some((words.join(" "), options))
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self) -> Result<Query, ParseError> {
+        let mut clauses = Vec::new();
+        loop {
+            while self.next_if(char::is_whitespace).is_some() {}
+            match self.peek() {
+                Some(_) => clauses.push(self.clause()?),
+                None => return Ok(Query { clauses }),
+            }
+        }
+    }
+
+    fn clause(&mut self) -> Result<Clause, ParseError> {
+        let start = self.pos();
+        let occur = if self.next_if(|ch| ch == '+').is_some() {
+            Occur::Must
+        } else if self.next_if(|ch| ch == '-').is_some() {
+            Occur::MustNot
+        } else {
+            Occur::Should
+        };
+
+        let mut field = None;
+        let term = if self.peek() == Some('"') {
+            self.phrase()?
+        } else {
+            let word_start = self.pos();
+            let word = self.word();
+            if self.next_if(|ch| ch == ':').is_some() {
+                if word.is_empty() {
+                    return Err(self.error(ParseErrorKind::MissingField, word_start));
+                }
+                field = Some(word.to_string());
+                if self.peek() == Some('"') {
+                    self.phrase()?
+                } else {
+                    self.required_word()?
+                }
+            } else if word.is_empty() {
+                return Err(self.error(ParseErrorKind::MissingTerm, start));
+            } else {
+                Term::Word(word.to_string())
+            }
+        };
+
+        let boost = if self.next_if(|ch| ch == '^').is_some() {
+            let boost_start = self.pos();
+            self.word()
+                .parse::<f64>()
+                .ok()
+                .filter(|boost| boost.is_finite() && *boost > 0.)
+                .ok_or_else(|| self.error(ParseErrorKind::InvalidBoost, boost_start - 1))?
+        } else {
+            1.
+        };
+
+        Ok(Clause {
+            occur,
+            field,
+            term,
+            boost,
+            span: start..self.pos(),
+        })
+    }
+
+    fn phrase(&mut self) -> Result<Term, ParseError> {
+        let start = self.pos();
+        self.chars.next();
+        let text_start = self.pos();
+        while self.next_if(|ch| ch != '"').is_some() {}
+        let text = &self.input[text_start..self.pos()];
+        if self.chars.next().is_none() {
+            return Err(self.error(ParseErrorKind::UnterminatedPhrase, start));
+        }
+        if text.trim().is_empty() {
+            return Err(self.error(ParseErrorKind::EmptyPhrase, start));
+        }
+        Ok(Term::Phrase(text.to_string()))
+    }
+
+    fn required_word(&mut self) -> Result<Term, ParseError> {
+        let start = self.pos();
+        let word = self.word();
+        if word.is_empty() {
+            return Err(self.error(ParseErrorKind::MissingTerm, start));
+        }
+        Ok(Term::Word(word.to_string()))
+    }
+
+    /// Consumes a word, up to the next whitespace or special character.
+    fn word(&mut self) -> &'a str {
+        let start = self.pos();
+        while self
+            .next_if(|ch| !ch.is_whitespace() && !matches!(ch, ':' | '^' | '"'))
+            .is_some()
+        {}
+        &self.input[start..self.pos()]
+    }
+
+    fn next_if(&mut self, f: impl Fn(char) -> bool) -> Option<char> {
+        match self.peek() {
+            Some(ch) if f(ch) => self.chars.next().map(|(_, ch)| ch),
+            _ => None,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, ch)| ch)
+    }
+
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.input.len(), |&(i, _)| i)
+    }
+
+    fn error(&mut self, kind: ParseErrorKind, start: usize) -> ParseError {
+        let end = self.pos().max(start + 1).min(self.input.len());
+        ParseError {
+            kind,
+            span: start..end,
+        }
+    }
+}
+
+impl Index {
+    /// Search the index with a parsed [`Query`], ordered from the best match to the worst.
+    ///
+    /// Each clause is searched separately, with [`search`](#method.search) for words and
+    /// [`search_phrase`](#method.search_phrase) for phrases, using `options` for the fields it
+    /// searches. The score of a document is the sum of the scores of the clauses it matches,
+    /// times their boosts.
+    ///
+    /// Like Lucene, a `+` clause which has no tokens after the pipeline, such as `+the`, is
+    /// ignored rather than excluding every document.
+    ///
+    /// Returns an error if a clause searches a field which is not in the index, or if the query
+    /// has a phrase and the index doesn't store positions.
+    pub fn search_query(
+        &self,
+        query: &Query,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let mut scores = BTreeMap::new();
+        let mut required: Option<BTreeSet<String>> = None;
+        let mut excluded = BTreeSet::new();
+
+        for clause in &query.clauses {
+            let options = self.clause_options(clause, options)?;
+            let (text, results) = match &clause.term {
+                Term::Word(word) => (word, self.search(word, &options)),
+                Term::Phrase(phrase) => (phrase, self.search_phrase(phrase, 0, &options)?),
+            };
+
+            match clause.occur {
+                Occur::MustNot => {
+                    excluded.extend(results.into_iter().map(|result| result.doc_ref));
+                    continue;
+                }
+                Occur::Must if !self.has_query_tokens(text, &options) => continue,
+                Occur::Must => {
+                    let matched = results.iter().map(|result| result.doc_ref.clone());
+                    required = Some(match required {
+                        Some(required) => matched.filter(|r| required.contains(r)).collect(),
+                        None => matched.collect(),
+                    });
+                }
+                Occur::Should => {}
+            }
+
+            for result in results {
+                *scores.entry(result.doc_ref).or_insert(0.) += result.score * clause.boost;
+            }
+        }
+
+        scores.retain(|doc_ref, _| {
+            !excluded.contains(doc_ref)
+                && required.as_ref().map_or(true, |req| req.contains(doc_ref))
+        });
+        Ok(search::sorted_results(scores))
+    }
+
+    /// Restricts `options` to the field of the clause, if it has one.
+    fn clause_options(
+        &self,
+        clause: &Clause,
+        options: &SearchOptions,
+    ) -> Result<SearchOptions, Error> {
+        let field = match &clause.field {
+            Some(field) => field,
+            None => return Ok(options.clone()),
+        };
+        if !self.fields.contains(field) {
+            return Err(Error::UnknownField(field.clone()));
+        }
+
+        let field_options = options
+            .fields
+            .as_ref()
+            .and_then(|fields| fields.get(field))
+            .copied()
+            .unwrap_or_default();
+        let mut fields = BTreeMap::new();
+        fields.insert(field.clone(), field_options);
+        Ok(SearchOptions {
+            fields: Some(fields),
+            ..options.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexBuilder;
+
+    fn clause(
+        occur: Occur,
+        field: Option<&str>,
+        term: Term,
+        boost: f64,
+        span: Range<usize>,
+    ) -> Clause {
+        Clause {
+            occur,
+            field: field.map(String::from),
+            term,
+            boost,
+            span,
+        }
+    }
+
+    fn word(word: &str) -> Term {
+        Term::Word(word.into())
+    }
+
+    #[test]
+    fn parsing_query() {
+        let query = Query::parse(
+            r#"title:rust +async  -deprecated docs^2 "exact phrase" -body:"old api"^0.5"#,
+        )
+        .unwrap();
+        assert_eq!(
+            query.clauses,
+            vec![
+                clause(Occur::Should, Some("title"), word("rust"), 1., 0..10),
+                clause(Occur::Must, None, word("async"), 1., 11..17),
+                clause(Occur::MustNot, None, word("deprecated"), 1., 19..30),
+                clause(Occur::Should, None, word("docs"), 2., 31..37),
+                clause(
+                    Occur::Should,
+                    None,
+                    Term::Phrase("exact phrase".into()),
+                    1.,
+                    38..52
+                ),
+                clause(
+                    Occur::MustNot,
+                    Some("body"),
+                    Term::Phrase("old api".into()),
+                    0.5,
+                    53..72
+                ),
+            ]
+        );
+
+        assert_eq!(Query::parse("").unwrap(), Query::default());
+        assert_eq!(Query::parse("  \t ").unwrap(), Query::default());
+        assert_eq!(
+            Query::parse("café-crème").unwrap().clauses,
+            vec![clause(Occur::Should, None, word("café-crème"), 1., 0..12)]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |query: &str| Query::parse(query).unwrap_err();
+        let parse_error = |kind, span| ParseError { kind, span };
+
+        assert_eq!(
+            error("rust +"),
+            parse_error(ParseErrorKind::MissingTerm, 5..6)
+        );
+        assert_eq!(
+            error("- rust"),
+            parse_error(ParseErrorKind::MissingTerm, 0..1)
+        );
+        assert_eq!(
+            error("title: rust"),
+            parse_error(ParseErrorKind::MissingTerm, 6..7)
+        );
+        assert_eq!(
+            error("rust :async"),
+            parse_error(ParseErrorKind::MissingField, 5..6)
+        );
+        assert_eq!(
+            error(r#"rust "async code"#),
+            parse_error(ParseErrorKind::UnterminatedPhrase, 5..16)
+        );
+        assert_eq!(
+            error(r#"rust " ""#),
+            parse_error(ParseErrorKind::EmptyPhrase, 5..8)
+        );
+        assert_eq!(
+            error("rust^"),
+            parse_error(ParseErrorKind::InvalidBoost, 4..5)
+        );
+        assert_eq!(
+            error("rust^-1 async"),
+            parse_error(ParseErrorKind::InvalidBoost, 4..7)
+        );
+        assert_eq!(
+            error("rust^abc"),
+            parse_error(ParseErrorKind::InvalidBoost, 4..8)
+        );
+        assert_eq!(error("^2"), parse_error(ParseErrorKind::MissingTerm, 0..1));
+
+        assert_eq!(
+            error("rust^0").to_string(),
+            "expected a positive number after `^` at 4..6"
+        );
+    }
+
+    #[test]
+    fn converting_to_search_options() {
+        let convert = |query: &str| Query::parse(query).unwrap().to_search_options();
+
+        let (query, options) = convert("async rust").unwrap();
+        assert_eq!(query, "async rust");
+        assert_eq!(options, SearchOptions::default());
+
+        let (query, options) = convert("+body:async +body:rust").unwrap();
+        assert_eq!(query, "async rust");
+        assert_eq!(options.bool, SearchBool::And);
+        assert_eq!(options.fields.unwrap().keys().collect::<Vec<_>>(), ["body"]);
+
+        let (_, options) = convert("+rust").unwrap();
+        assert_eq!(options.bool, SearchBool::And);
+        assert_eq!(options.fields, None);
+
+        assert!(convert("").is_none());
+        assert!(convert("+async +rust").is_none());
+        assert!(convert("async +rust").is_none());
+        assert!(convert("async -rust").is_none());
+        assert!(convert("title:async rust").is_none());
+        assert!(convert("async^2 rust").is_none());
+        assert!(convert(r#""async rust""#).is_none());
+    }
+
+    fn query_index() -> Index {
+        let mut index = IndexBuilder::new()
+            .store_positions(true)
+            .add_fields(&["title", "body"])
+            .build();
+        index.add_doc("1", &["Async Rust", "Writing async code with futures"]);
+        index.add_doc("2", &["Old Rust", "Deprecated async code with tasks"]);
+        index.add_doc("3", &["Rust docs", "How to write documentation"]);
+        index.add_doc("4", &["Python", "Async code in Python"]);
+        index
+    }
+
+    fn search(index: &Index, query: &str) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        index
+            .search_query(&query, &SearchOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|result| result.doc_ref)
+            .collect()
+    }
+
+    #[test]
+    fn searching_with_query() {
+        let index = query_index();
+        assert_eq!(search(&index, "rust"), ["1", "2", "3"]);
+        assert_eq!(search(&index, "title:async"), ["1"]);
+        assert_eq!(search(&index, "rust +async"), ["1", "2", "4"]);
+        assert_eq!(search(&index, "rust -deprecated"), ["1", "3"]);
+        assert_eq!(search(&index, "+rust +async -deprecated"), ["1"]);
+        assert_eq!(search(&index, r#""async code" -python"#), ["1", "2"]);
+        assert_eq!(search(&index, "-rust"), Vec::<String>::new());
+        assert_eq!(search(&index, ""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn searching_with_required_stop_words() {
+        let index = query_index();
+        assert_eq!(search(&index, "+the rust"), ["1", "2", "3"]);
+        assert_eq!(search(&index, r#"+"to the" +rust"#), ["1", "2", "3"]);
+        assert_eq!(
+            search(&index, "+title:the +async"),
+            search(&index, "+async")
+        );
+        assert_eq!(search(&index, "+the"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn searching_with_boosts() {
+        let index = query_index();
+        let query = Query::parse("title:python body:deprecated").unwrap();
+        let results = index
+            .search_query(&query, &SearchOptions::default())
+            .unwrap();
+        let boosted = Query::parse("title:python body:deprecated^10").unwrap();
+        let boosted_results = index
+            .search_query(&boosted, &SearchOptions::default())
+            .unwrap();
+
+        assert_eq!(results[0].doc_ref, "4");
+        assert_eq!(boosted_results[0].doc_ref, "2");
+        assert_eq!(boosted_results[0].score, results[1].score * 10.);
+    }
+
+    #[test]
+    fn search_query_errors() {
+        let index = query_index();
+        let query = Query::parse("tags:rust").unwrap();
+        assert!(matches!(
+            index.search_query(&query, &SearchOptions::default()),
+            Err(Error::UnknownField(field)) if field == "tags"
+        ));
+
+        let index = Index::new(&["title", "body"]);
+        let query = Query::parse(r#""async rust""#).unwrap();
+        assert!(matches!(
+            index.search_query(&query, &SearchOptions::default()),
+            Err(Error::PositionsNotStored)
+        ));
+    }
+}
@@ -0,0 +1,1074 @@
+//! Implements elasticlunr.js searching for an [`Index`]. Most users only need
+//! [`Index::search`].
+//!
+//! *Reference:*
+//! <http://elasticlunr.com/docs/index.js.html>
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::config::{FuzzyOptions, SearchBool, SearchOptions};
+use crate::inverted_index::InvertedIndex;
+use crate::{Error, Index};
+
+/// A document matched by [`Index::search`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    #[serde(rename = "ref")]
+    pub doc_ref: String,
+    pub score: f64,
+}
+
+/// Why a document has the score it does for a query. Returned by [`Index::explain`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Explanation {
+    #[serde(rename = "ref")]
+    pub doc_ref: String,
+    /// The score of the document, which is the sum of the scores of its fields. This is the
+    /// same as the score returned by [`Index::search`], or 0 if the document doesn't match.
+    pub score: f64,
+    /// The explanation of each searched field.
+    pub fields: Vec<FieldExplanation>,
+}
+
+/// How a field of a document was scored. Part of an [`Explanation`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldExplanation {
+    pub field: String,
+    /// The boolean model used to combine the query tokens in this field.
+    pub bool: SearchBool,
+    pub boost: f64,
+    /// Whether the document matches the query in this field. With the `AND` model this is only
+    /// true if the field contains every query token.
+    pub matched: bool,
+    /// The fraction of the query tokens found in the field, which the sum of the scores of the
+    /// terms is multiplied by.
+    pub coordination: f64,
+    /// The score of the field: the sum of the scores of the terms, times the coordination and
+    /// the boost. This is 0 if the field didn't match.
+    pub score: f64,
+    /// The tokens of the field which matched a query token.
+    pub terms: Vec<TermExplanation>,
+}
+
+/// How a single token of a field was scored. Part of a [`FieldExplanation`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TermExplanation {
+    /// The token of the query, after it was processed by the pipeline.
+    pub query_token: String,
+    /// The token of the field which matched `query_token`. This is only different from
+    /// `query_token` if the token was expanded or fuzzy matched.
+    pub token: String,
+    /// The term frequency of `token` in the field, which is the square root of the number of
+    /// times it appears.
+    pub tf: f64,
+    /// The inverse document frequency of `token` in the field.
+    pub idf: f64,
+    /// `1 / sqrt(length)`, where `length` is the number of tokens in the field.
+    pub field_length_norm: f64,
+    /// The penalty for matching a different token than `query_token`, or 1.
+    pub weight: f64,
+    /// The score of the term: `tf * idf * field_length_norm * weight`.
+    pub score: f64,
+}
+
+/// The search configuration of a single field, after applying the defaults.
+#[derive(Debug, Copy, Clone)]
+struct FieldConfig {
+    boost: f64,
+    bool: SearchBool,
+    expand: bool,
+    fuzzy: Option<FuzzyOptions>,
+}
+
+impl Index {
+    /// Search the index for documents matching `query`, ordered from the best match to the worst.
+    ///
+    /// The query is processed by the same tokenizer and [`Pipeline`](pipeline/struct.Pipeline.html)
+    /// used to index each field, and the results are scored the same way as elasticlunr.js: the
+    /// tf-idf of each query token is normalized by the length of the field, multiplied by the
+    /// field's boost, and scaled by the fraction of query tokens found in the document.
+    ///
+    /// If `options.fields` is `None` every field in the index is searched with a boost of 1, and
+    /// if it is an empty map no field is searched, like elasticlunr.js. Fields which are not in
+    /// the index are ignored, as are fields with a boost of 0.
+    ///
+    /// The `bool` of each field selects how its query tokens are combined: with
+    /// [`SearchBool::Or`] a document matches the field if it contains any of the tokens, and with
+    /// [`SearchBool::And`] only if it contains all of them. The results of each field are always
+    /// combined with `OR`, so a document needs to match only one field.
+    ///
+    /// When `expand` is enabled for a field, each query token also matches every token in the
+    /// field which starts with it. Like elasticlunr.js, the expanded tokens are penalized by how
+    /// much longer they are than the query token, and don't count towards coordination.
+    ///
+    /// The `bool` and `expand` of a field default to the global options when they are `None`.
+    ///
+    /// Unlike elasticlunr.js, query tokens can also match tokens which are spelled slightly
+    /// differently, by setting `options.fuzzy`. See [`FuzzyOptions`] for how they are scored.
+    ///
+    /// Results with the same score are ordered like the keys of a JavaScript object, as in
+    /// elasticlunr.js: references which are integers come first, in numeric order, so `"2"` comes
+    /// before `"10"`. Other references come after them in lexical order, while elasticlunr.js
+    /// keeps them in the order the documents were added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elasticlunr::{Index, config::SearchOptions};
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["Apples", "An apple a day keeps the doctor away"]);
+    /// index.add_doc("2", &["Bananas", "Bananas are berries, botanically speaking"]);
+    ///
+    /// let results = index.search("apple", &SearchOptions::default());
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(results[0].doc_ref, "1");
+    /// ```
+    pub fn search(&self, query: &str, options: &SearchOptions) -> Vec<SearchResult> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scores = BTreeMap::new();
+        for (field, config) in self.field_configs(options) {
+            if config.boost == 0. {
+                continue;
+            }
+
+            let tokens = self.pipeline.run(self.raw_query_tokens(field, query));
+            for (doc_ref, score) in self.field_search(&tokens, field, config) {
+                *scores.entry(doc_ref).or_insert(0.) += score * config.boost;
+            }
+        }
+
+        sorted_results(scores)
+    }
+
+    /// Explains how `query` scores the document `doc_ref`, for debugging the ranking of search
+    /// results. The explanation contains the same factors and score as
+    /// [`search`](#method.search) with the same `options`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elasticlunr::{Index, config::SearchOptions};
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["Apples", "An apple a day keeps the doctor away"]);
+    /// index.add_doc("2", &["Bananas", "Bananas are berries, botanically speaking"]);
+    ///
+    /// let options = SearchOptions::default();
+    /// let explanation = index.explain("apple berries", "1", &options);
+    /// assert_eq!(explanation.score, index.search("apple berries", &options)[0].score);
+    ///
+    /// let title = &explanation.fields[0];
+    /// assert_eq!(title.field, "title");
+    /// assert_eq!(title.coordination, 0.5);
+    /// assert_eq!(title.terms[0].token, "appl");
+    /// ```
+    pub fn explain(&self, query: &str, doc_ref: &str, options: &SearchOptions) -> Explanation {
+        let mut explanation = Explanation {
+            doc_ref: doc_ref.to_string(),
+            score: 0.,
+            fields: Vec::new(),
+        };
+        if query.is_empty() {
+            return explanation;
+        }
+
+        for (field, config) in self.field_configs(options) {
+            if config.boost == 0. {
+                continue;
+            }
+
+            let tokens = self.pipeline.run(self.raw_query_tokens(field, query));
+            let mut terms = Vec::new();
+            let mut record_term = |term_doc_ref: &str, term: &TermScore| {
+                if term_doc_ref == doc_ref {
+                    terms.push(TermExplanation {
+                        query_token: term.query_token.to_string(),
+                        token: term.token.to_string(),
+                        tf: term.tf,
+                        idf: term.idf,
+                        field_length_norm: term.field_length_norm,
+                        weight: term.weight,
+                        score: term.score(),
+                    });
+                }
+            };
+
+            // With `AND`, documents missing a token are skipped before their later tokens are
+            // scored, so find all of the terms with `OR`.
+            let field_score = if config.bool == SearchBool::And {
+                let or_config = FieldConfig {
+                    bool: SearchBool::Or,
+                    ..config
+                };
+                self.field_search_with(&tokens, field, or_config, &mut record_term);
+                self.field_search(&tokens, field, config)
+            } else {
+                self.field_search_with(&tokens, field, config, &mut record_term)
+            }
+            .remove(doc_ref);
+
+            let matched = terms
+                .iter()
+                .filter(|term| term.token == term.query_token)
+                .count();
+            let coordination = if matched != 0 {
+                matched as f64 / tokens.len() as f64
+            } else {
+                1.
+            };
+            let score = field_score.map_or(0., |score| score * config.boost);
+            explanation.score += score;
+            explanation.fields.push(FieldExplanation {
+                field: field.to_string(),
+                bool: config.bool,
+                boost: config.boost,
+                matched: field_score.is_some(),
+                coordination,
+                score,
+                terms,
+            });
+        }
+
+        explanation
+    }
+
+    /// Search the index for documents containing `phrase`, ordered from the best match to the
+    /// worst. The index must have been built with
+    /// [`IndexBuilder::store_positions`](crate::IndexBuilder::store_positions).
+    ///
+    /// A document matches a field if the tokens of the phrase appear in it in the same order and
+    /// with the same distance between them as in the phrase. Tokens removed by the pipeline, like
+    /// stop words, still count towards the distance. `slop` is the number of extra positions
+    /// allowed between the first and last token of the phrase, so with a `slop` of 0 the phrase
+    /// has to match exactly.
+    ///
+    /// Matching documents are scored like [`search`](#method.search) with the `AND` model. The
+    /// fields and boosts of `options` are used, but `bool`, `expand` and `fuzzy` are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elasticlunr::{IndexBuilder, config::SearchOptions};
+    /// let mut index = IndexBuilder::new()
+    ///     .store_positions(true)
+    ///     .add_fields(&["title", "body"])
+    ///     .build();
+    /// index.add_doc("1", &["Errors", "Error handling in Rust"]);
+    /// index.add_doc("2", &["Panics", "Handling a panic is not error recovery"]);
+    ///
+    /// let options = SearchOptions::default();
+    /// let results = index.search_phrase("error handling", 0, &options).unwrap();
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(results[0].doc_ref, "1");
+    /// ```
+    pub fn search_phrase(
+        &self,
+        phrase: &str,
+        slop: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, Error> {
+        if !self.store_positions {
+            return Err(Error::PositionsNotStored);
+        }
+
+        let mut scores = BTreeMap::new();
+        for (field, config) in self.field_configs(options) {
+            if config.boost == 0. {
+                continue;
+            }
+
+            let (positions, tokens): (Vec<_>, Vec<_>) = self
+                .pipeline
+                .run_with_positions(self.raw_query_tokens(field, phrase))
+                .into_iter()
+                .unzip();
+            if tokens.is_empty() {
+                continue;
+            }
+
+            let config = FieldConfig {
+                bool: SearchBool::And,
+                expand: false,
+                fuzzy: None,
+                ..config
+            };
+            for (doc_ref, score) in self.field_search(&tokens, field, config) {
+                if self.phrase_matches(field, &doc_ref, &tokens, &positions, slop) {
+                    *scores.entry(doc_ref).or_insert(0.) += score * config.boost;
+                }
+            }
+        }
+
+        Ok(sorted_results(scores))
+    }
+
+    /// Resolves the configuration of each searched field, like elasticlunr.js's `Configuration`.
+    fn field_configs<'a>(&'a self, options: &'a SearchOptions) -> Vec<(&'a str, FieldConfig)> {
+        let fields = match &options.fields {
+            Some(fields) => fields,
+            None => {
+                return self
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let config = FieldConfig {
+                            boost: 1.,
+                            bool: options.bool,
+                            expand: options.expand,
+                            fuzzy: options.fuzzy,
+                        };
+                        (field.as_str(), config)
+                    })
+                    .collect();
+            }
+        };
+
+        fields
+            .iter()
+            .filter(|(field, _)| self.index.contains_key(*field))
+            .map(|(field, opts)| {
+                let config = FieldConfig {
+                    boost: opts.boost.map_or(1., f64::from),
+                    bool: opts.bool.unwrap_or(options.bool),
+                    expand: opts.expand.unwrap_or(options.expand),
+                    fuzzy: options.fuzzy,
+                };
+                (field.as_str(), config)
+            })
+            .collect()
+    }
+
+    /// Returns whether `query` has any tokens left after the pipeline in a field searched with
+    /// `options`. A query of only stop words has none.
+    pub(crate) fn has_query_tokens(&self, query: &str, options: &SearchOptions) -> bool {
+        self.field_configs(options)
+            .into_iter()
+            .filter(|(_, config)| config.boost != 0.)
+            .any(|(field, _)| {
+                !self
+                    .pipeline
+                    .run(self.raw_query_tokens(field, query))
+                    .is_empty()
+            })
+    }
+
+    /// Splits the query into tokens with the tokenizer used for the text of `field`. The tokens
+    /// still need to be run through the pipeline.
+    pub(crate) fn raw_query_tokens(&self, field: &str, query: &str) -> Vec<String> {
+        let tokenizer = self
+            .fields
+            .iter()
+            .position(|f| f == field)
+            .and_then(|i| self.field_tokenizers.get(i))
+            .and_then(Option::as_ref);
+
+        if let Some(tokenizer) = tokenizer {
+            tokenizer(query)
+        } else {
+            self.lang.tokenize(query)
+        }
+    }
+
+    /// Scores the documents matching `tokens` in `field`. With the `OR` model every document
+    /// containing at least one of the tokens matches, and with the `AND` model only documents
+    /// containing all of them do.
+    fn field_search(
+        &self,
+        tokens: &[String],
+        field: &str,
+        config: FieldConfig,
+    ) -> BTreeMap<String, f64> {
+        self.field_search_with(tokens, field, config, |_, _| {})
+    }
+
+    /// Scores the documents matching `tokens` in `field` like [`field_search`], and calls
+    /// `on_term` with the score of each token found in each document before it's combined with
+    /// the others.
+    fn field_search_with<F>(
+        &self,
+        tokens: &[String],
+        field: &str,
+        config: FieldConfig,
+        mut on_term: F,
+    ) -> BTreeMap<String, f64>
+    where
+        F: FnMut(&str, &TermScore),
+    {
+        let index = &self.index[field];
+        let mut scores: Option<BTreeMap<String, f64>> = None;
+        let mut doc_tokens = BTreeMap::new();
+
+        for token in tokens {
+            let mut token_scores = BTreeMap::new();
+            for (key, weight) in matching_tokens(index, token, config) {
+                let mut docs = index.get_docs(&key).unwrap_or_default();
+                if let (Some(scores), SearchBool::And) = (&scores, config.bool) {
+                    // Documents missing an earlier token can't match anymore.
+                    docs.retain(|doc_ref, _| scores.contains_key(doc_ref));
+                }
+
+                let idf = self.idf(&key, field);
+
+                for (doc_ref, tf) in docs {
+                    // Only the query token itself counts towards coordination.
+                    if key == *token {
+                        *doc_tokens.entry(doc_ref.clone()).or_insert(0usize) += 1;
+                    }
+
+                    let field_length = self.document_store.get_field_length(&doc_ref, field);
+                    let field_length_norm = if field_length != 0 {
+                        1. / (field_length as f64).sqrt()
+                    } else {
+                        1.
+                    };
+
+                    let term = TermScore {
+                        query_token: token,
+                        token: &key,
+                        tf,
+                        idf,
+                        field_length_norm,
+                        weight,
+                    };
+                    on_term(&doc_ref, &term);
+                    *token_scores.entry(doc_ref).or_insert(0.) += term.score();
+                }
+            }
+
+            scores = Some(match scores {
+                Some(scores) => merge_scores(scores, token_scores, config.bool),
+                None => token_scores,
+            });
+        }
+
+        // Coordination: favor documents which contain more of the query tokens.
+        let mut scores = scores.unwrap_or_default();
+        let token_count = tokens.len() as f64;
+        for (doc_ref, score) in scores.iter_mut() {
+            if let Some(&matched) = doc_tokens.get(doc_ref) {
+                *score *= matched as f64 / token_count;
+            }
+        }
+
+        scores
+    }
+
+    /// Checks whether `tokens` appear in `field` of the document in order, with the distances
+    /// between them given by `positions`, plus at most `slop` extra positions.
+    fn phrase_matches(
+        &self,
+        field: &str,
+        doc_ref: &str,
+        tokens: &[String],
+        positions: &[usize],
+        slop: usize,
+    ) -> bool {
+        let index = &self.index[field];
+        let doc_positions: Vec<_> = tokens
+            .iter()
+            .map(|token| index.get_positions(doc_ref, token))
+            .collect();
+        let phrase_len = positions[positions.len() - 1] - positions[0];
+
+        doc_positions[0].iter().any(|&start| {
+            // Taking the earliest possible position of each token gives the shortest match.
+            let mut end = start;
+            for (i, token_positions) in doc_positions.iter().enumerate().skip(1) {
+                let min = end + positions[i] - positions[i - 1];
+                let next = token_positions.partition_point(|&position| position < min);
+                match token_positions.get(next) {
+                    Some(&position) => end = position,
+                    None => return false,
+                }
+            }
+            end - start <= phrase_len + slop
+        })
+    }
+
+    /// The inverse document frequency of `token` in `field`.
+    fn idf(&self, token: &str, field: &str) -> f64 {
+        let doc_freq = self.index[field].get_doc_frequency(token);
+        1. + (self.document_store.len() as f64 / (doc_freq + 1) as f64).ln()
+    }
+}
+
+/// The score of a token of the index in a document, before coordination.
+struct TermScore<'a> {
+    query_token: &'a str,
+    token: &'a str,
+    tf: f64,
+    idf: f64,
+    field_length_norm: f64,
+    weight: f64,
+}
+
+impl TermScore<'_> {
+    fn score(&self) -> f64 {
+        self.weight * self.tf * self.idf * self.field_length_norm
+    }
+}
+
+/// Converts the scores of each document to results, ordered from the best match to the worst.
+/// Results with the same score are ordered by [`cmp_refs`].
+pub(crate) fn sorted_results(scores: BTreeMap<String, f64>) -> Vec<SearchResult> {
+    let mut results: Vec<_> = scores
+        .into_iter()
+        .map(|(doc_ref, score)| SearchResult { doc_ref, score })
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| cmp_refs(&a.doc_ref, &b.doc_ref))
+    });
+    results
+}
+
+/// Orders document references like the keys of a JavaScript object, which elasticlunr.js sorts
+/// its results by when their scores are equal: array indexes first in numeric order, then the
+/// other keys. JavaScript keeps the other keys in insertion order, which is not known here, so
+/// they are in lexical order.
+fn cmp_refs(a: &str, b: &str) -> Ordering {
+    match (array_index(a), array_index(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Parses a JavaScript array index: an integer below 2^32 - 1 without leading zeros.
+fn array_index(key: &str) -> Option<u32> {
+    let index: u32 = key.parse().ok()?;
+    if index == u32::MAX || index.to_string() != key {
+        return None;
+    }
+    Some(index)
+}
+
+/// Combines the scores of a query token with the scores of the tokens before it. Like
+/// elasticlunr.js's `mergeScores`, the `AND` model keeps only the documents found in both.
+fn merge_scores(
+    mut scores: BTreeMap<String, f64>,
+    token_scores: BTreeMap<String, f64>,
+    bool: SearchBool,
+) -> BTreeMap<String, f64> {
+    match bool {
+        SearchBool::And => token_scores
+            .into_iter()
+            .filter_map(|(doc_ref, score)| {
+                let total = scores.get(&doc_ref)? + score;
+                Some((doc_ref, total))
+            })
+            .collect(),
+        SearchBool::Or => {
+            for (doc_ref, score) in token_scores {
+                *scores.entry(doc_ref).or_insert(0.) += score;
+            }
+            scores
+        }
+    }
+}
+
+/// Finds the tokens of `index` which match the query token `token`, along with the weight of
+/// each match. A token found more than one way keeps its highest weight.
+fn matching_tokens(
+    index: &InvertedIndex,
+    token: &str,
+    config: FieldConfig,
+) -> BTreeMap<String, f64> {
+    let mut matches = BTreeMap::new();
+    let mut add_match = |key: String, weight: f64| {
+        if weight == 0. {
+            return;
+        }
+        let entry = matches.entry(key).or_insert(weight);
+        *entry = entry.max(weight);
+    };
+
+    if index.get_doc_frequency(token) > 0 {
+        add_match(token.to_string(), 1.);
+    }
+    if config.expand {
+        for key in index.expand_token(token) {
+            let weight = expansion_penalty(token, &key);
+            add_match(key, weight);
+        }
+    }
+    if let Some(fuzzy) = config.fuzzy {
+        let edit_weight = 1. - f64::from(fuzzy.penalty.min(100)) / 100.;
+        for (key, edits) in index.fuzzy_terms(token, fuzzy.max_edits.into()) {
+            add_match(key, edit_weight.powi(edits as i32));
+        }
+    }
+
+    matches
+}
+
+/// The weight of `key` when it was found by expanding the query token `token`. Longer expansions
+/// are weighted less, and every expansion is weighted much less than the token itself. Lengths
+/// are in UTF-16 code units, like the `length` of a JavaScript string.
+fn expansion_penalty(token: &str, key: &str) -> f64 {
+    if key == token {
+        return 1.;
+    }
+
+    let key_len = key.encode_utf16().count() as f64;
+    let extra_len = key_len - token.encode_utf16().count() as f64;
+    (1. - extra_len / key_len) * 0.15
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SearchOptionsField;
+    use crate::IndexBuilder;
+
+    // Scores were generated by elasticlunr.js 0.9.5 from the same documents.
+    fn oracle_index() -> Index {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc(
+            "1",
+            &[
+                "Oracle released its latest database Oracle 12g",
+                "Yesterday Oracle has released its new database Oracle 12g, this would make more \
+                 money for this company and lead to a nice profit report of annual year.",
+            ],
+        );
+        index.add_doc(
+            "2",
+            &[
+                "Oracle released its profit report of 2015",
+                "As expected, Oracle released its profit report of 2015, during the good sales of \
+                 database and hardware, Oracle profit of 2015 reached 12.5 Billion.",
+            ],
+        );
+        index.add_doc("3", &["Apple sells apples", "apple profit database"]);
+        index
+    }
+
+    fn assert_results(results: &[SearchResult], expected: &[(&str, f64)]) {
+        assert_eq!(results.len(), expected.len());
+        for (result, &(doc_ref, score)) in results.iter().zip(expected) {
+            assert_eq!(result.doc_ref, doc_ref);
+            assert!(
+                (result.score - score).abs() < 1e-12,
+                "{}: {} != {}",
+                doc_ref,
+                result.score,
+                score
+            );
+        }
+    }
+
+    #[test]
+    fn search_default_options() {
+        let index = oracle_index();
+        let results = index.search("Oracle database profit", &SearchOptions::default());
+        assert_results(
+            &results,
+            &[
+                ("2", 1.4772538107910025),
+                ("1", 1.4559414491042646),
+                ("3", 0.5483425962914142),
+            ],
+        );
+    }
+
+    #[test]
+    fn search_with_boosts() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            fields: Some(btreemap! {
+                "title".into() => SearchOptionsField {
+                    boost: Some(2),
+                    ..Default::default()
+                },
+                "body".into() => SearchOptionsField {
+                    boost: Some(1),
+                    ..Default::default()
+                },
+            }),
+            ..Default::default()
+        };
+        let results = index.search("Oracle database profit", &options);
+        assert_results(
+            &results,
+            &[
+                ("1", 2.2233607803585262),
+                ("2", 2.1944249440221673),
+                ("3", 0.5483425962914142),
+            ],
+        );
+    }
+
+    #[test]
+    fn search_is_stemmed() {
+        let index = oracle_index();
+        let results = index.search("apple", &SearchOptions::default());
+        assert_results(&results, &[("3", 1.9590031138864086)]);
+    }
+
+    #[test]
+    fn search_only_configured_fields() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            fields: Some(btreemap! {
+                "title".into() => SearchOptionsField::default(),
+                "missing".into() => SearchOptionsField::default(),
+            }),
+            ..Default::default()
+        };
+        let results = index.search("hardware", &options);
+        assert!(results.is_empty());
+
+        let results = index.search("2015", &options);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].doc_ref, "2");
+    }
+
+    #[test]
+    fn search_zero_boost_is_ignored() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            fields: Some(btreemap! {
+                "title".into() => SearchOptionsField {
+                    boost: Some(0),
+                    ..Default::default()
+                },
+            }),
+            ..Default::default()
+        };
+        assert!(index.search("oracle", &options).is_empty());
+    }
+
+    #[test]
+    fn search_expand() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            expand: true,
+            ..Default::default()
+        };
+        assert_results(
+            &index.search("data pro", &options),
+            &[
+                ("2", 0.08027317237643311),
+                ("1", 0.07694643137433774),
+                ("3", 0.06609486651726867),
+            ],
+        );
+        assert_results(
+            &index.search("re", &options),
+            &[("2", 0.0984939190036769), ("1", 0.04466597702682645)],
+        );
+    }
+
+    #[test]
+    fn equal_scores_are_ordered_like_js_keys() {
+        let mut index = Index::new(&["title"]);
+        for doc_ref in &["b", "10", "a", "2", "01", "1", "+3"] {
+            index.add_doc(doc_ref, &["apple"]);
+        }
+        let results = index.search("apple", &SearchOptions::default());
+        let refs: Vec<_> = results.iter().map(|r| r.doc_ref.as_str()).collect();
+        // elasticlunr.js orders the last four as "b", "a", "01", "+3", the order they were added.
+        assert_eq!(refs, ["1", "2", "10", "+3", "01", "a", "b"]);
+
+        assert_eq!(array_index("4294967294"), Some(4294967294));
+        assert_eq!(array_index("4294967295"), None);
+        assert_eq!(array_index("-1"), None);
+    }
+
+    #[test]
+    fn expansion_penalty_counts_utf16() {
+        assert_eq!(expansion_penalty("data", "data"), 1.);
+        assert_eq!(expansion_penalty("data", "database"), 0.5 * 0.15);
+        // "🍎" is two UTF-16 code units, so "a🍎" has a length of 3 in JavaScript.
+        assert_eq!(expansion_penalty("a", "a🍎"), (1. - 2. / 3.) * 0.15);
+    }
+
+    #[test]
+    fn search_expand_per_field() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            fields: Some(btreemap! {
+                "title".into() => SearchOptionsField {
+                    boost: Some(2),
+                    expand: Some(true),
+                    ..Default::default()
+                },
+                "body".into() => SearchOptionsField {
+                    boost: Some(1),
+                    ..Default::default()
+                },
+            }),
+            ..Default::default()
+        };
+        assert_results(
+            &index.search("data pro", &options),
+            &[("1", 0.09836206760430284), ("2", 0.09428146565201839)],
+        );
+    }
+
+    #[test]
+    fn search_and() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            bool: SearchBool::And,
+            ..Default::default()
+        };
+        assert_results(
+            &index.search("Oracle database profit", &options),
+            &[("2", 0.7600826775598377), ("1", 0.688522117850003)],
+        );
+        assert!(index.search("oracle apple", &options).is_empty());
+    }
+
+    #[test]
+    fn search_and_per_field() {
+        let index = oracle_index();
+        let options = SearchOptions {
+            fields: Some(btreemap! {
+                "title".into() => SearchOptionsField {
+                    bool: Some(SearchBool::And),
+                    ..Default::default()
+                },
+                "body".into() => SearchOptionsField::default(),
+            }),
+            ..Default::default()
+        };
+        assert_results(
+            &index.search("apple database", &options),
+            &[
+                ("3", 1.222702605721436),
+                ("1", 0.08638123689124633),
+                ("2", 0.08638123689124633),
+            ],
+        );
+    }
+
+    #[test]
+    fn search_fuzzy() {
+        let index = oracle_index();
+        let exact = index.search("oracle", &SearchOptions::default());
+        assert!(index.search("orcale", &SearchOptions::default()).is_empty());
+
+        let mut options = SearchOptions {
+            fuzzy: Some(FuzzyOptions::default()),
+            ..Default::default()
+        };
+        assert_eq!(index.search("oracle", &options), exact);
+        assert!(index.search("orcale", &options).is_empty());
+
+        options.fuzzy = Some(FuzzyOptions {
+            max_edits: 2,
+            penalty: 50,
+        });
+        let results = index.search("orcale", &options);
+        assert_eq!(results.len(), exact.len());
+        for (result, exact) in results.iter().zip(&exact) {
+            assert_eq!(result.doc_ref, exact.doc_ref);
+            assert!((result.score - exact.score * 0.25).abs() < 1e-12);
+        }
+
+        options.fuzzy = Some(FuzzyOptions {
+            max_edits: 2,
+            penalty: 100,
+        });
+        assert!(index.search("orcale", &options).is_empty());
+        assert_eq!(index.search("oracle", &options), exact);
+    }
+
+    #[test]
+    fn explain_term_scores() {
+        let index = oracle_index();
+        let explanation = index.explain("apple", "3", &SearchOptions::default());
+        assert_eq!(explanation.doc_ref, "3");
+        assert_eq!(explanation.score, 1.9590031138864086);
+        assert_eq!(explanation.fields.len(), 2);
+
+        let title = &explanation.fields[0];
+        assert_eq!(title.field, "title");
+        assert!(title.matched);
+        assert_eq!(title.coordination, 1.);
+        assert_eq!(
+            title.terms,
+            [TermExplanation {
+                query_token: "appl".into(),
+                token: "appl".into(),
+                tf: 2f64.sqrt(),
+                idf: 1. + (3f64 / 2.).ln(),
+                field_length_norm: 1. / 3f64.sqrt(),
+                weight: 1.,
+                score: title.score,
+            }]
+        );
+        assert_eq!(
+            explanation.score,
+            explanation.fields[0].score + explanation.fields[1].score
+        );
+
+        let explanation = index.explain("apple", "1", &SearchOptions::default());
+        assert_eq!(explanation.score, 0.);
+        assert!(explanation.fields.iter().all(|field| !field.matched));
+        assert!(explanation
+            .fields
+            .iter()
+            .all(|field| field.terms.is_empty()));
+    }
+
+    #[test]
+    fn explain_matches_search() {
+        let index = oracle_index();
+        let all_options = [
+            SearchOptions::default(),
+            SearchOptions {
+                bool: SearchBool::And,
+                ..Default::default()
+            },
+            SearchOptions {
+                expand: true,
+                fuzzy: Some(FuzzyOptions::default()),
+                ..Default::default()
+            },
+            SearchOptions {
+                fields: Some(btreemap! {
+                    "title".into() => SearchOptionsField {
+                        boost: Some(2),
+                        bool: Some(SearchBool::And),
+                        ..Default::default()
+                    },
+                    "body".into() => SearchOptionsField {
+                        expand: Some(true),
+                        ..Default::default()
+                    },
+                }),
+                ..Default::default()
+            },
+        ];
+
+        for options in &all_options {
+            for query in &["Oracle database profit", "data pro", "apple oracle"] {
+                let results = index.search(query, options);
+                for doc_ref in &["1", "2", "3"] {
+                    let explanation = index.explain(query, doc_ref, options);
+                    let score = results
+                        .iter()
+                        .find(|result| result.doc_ref == *doc_ref)
+                        .map_or(0., |result| result.score);
+                    assert_eq!(
+                        explanation.score, score,
+                        "{} {} {:?}",
+                        query, doc_ref, options
+                    );
+                }
+            }
+        }
+
+        // Terms of fields which don't match with `AND` are still explained.
+        let explanation = index.explain("apple oracle", "3", &all_options[1]);
+        let title = &explanation.fields[0];
+        assert!(!title.matched);
+        assert_eq!(title.score, 0.);
+        assert_eq!(title.coordination, 0.5);
+        assert_eq!(title.terms.len(), 1);
+        assert_eq!(title.terms[0].token, "appl");
+    }
+
+    fn phrase_index() -> Index {
+        let mut index = IndexBuilder::new()
+            .store_positions(true)
+            .add_fields(&["title", "body"])
+            .build();
+        index.add_doc("1", &["Error handling", "How to deal with errors in Rust"]);
+        index.add_doc("2", &["Handling errors", "Error and panic handling"]);
+        index.add_doc(
+            "3",
+            &["Recovering", "The state of the art of error handling"],
+        );
+        index
+    }
+
+    fn refs(results: &[SearchResult]) -> Vec<&str> {
+        let mut refs: Vec<_> = results.iter().map(|r| r.doc_ref.as_str()).collect();
+        refs.sort_unstable();
+        refs
+    }
+
+    #[test]
+    fn search_phrase() {
+        let index = phrase_index();
+        let options = SearchOptions::default();
+
+        let results = index.search_phrase("error handling", 0, &options).unwrap();
+        assert_eq!(refs(&results), ["1", "3"]);
+        let results = index.search_phrase("handling errors", 0, &options).unwrap();
+        assert_eq!(refs(&results), ["2"]);
+        let results = index
+            .search_phrase("state of the art", 0, &options)
+            .unwrap();
+        assert_eq!(refs(&results), ["3"]);
+        let results = index.search_phrase("state art", 0, &options).unwrap();
+        assert!(results.is_empty());
+        let results = index.search_phrase("of the", 0, &options).unwrap();
+        assert!(results.is_empty());
+
+        // A phrase is scored like an `AND` search of its tokens.
+        let options = SearchOptions {
+            fields: Some(btreemap! {
+                "title".into() => SearchOptionsField::default(),
+            }),
+            ..Default::default()
+        };
+        let and_options = SearchOptions {
+            bool: SearchBool::And,
+            ..options.clone()
+        };
+        assert_eq!(
+            index.search_phrase("error handling", 0, &options).unwrap(),
+            index.search("error handling", &and_options)[..1]
+        );
+    }
+
+    #[test]
+    fn search_phrase_slop() {
+        let index = phrase_index();
+        let options = SearchOptions::default();
+
+        let results = index.search_phrase("error handling", 1, &options).unwrap();
+        assert_eq!(refs(&results), ["1", "3"]);
+        let results = index.search_phrase("error handling", 3, &options).unwrap();
+        assert_eq!(refs(&results), ["1", "2", "3"]);
+        let results = index.search_phrase("state art", 2, &options).unwrap();
+        assert_eq!(refs(&results), ["3"]);
+        // The tokens still have to be in order.
+        let results = index.search_phrase("handling error", 10, &options).unwrap();
+        assert_eq!(refs(&results), ["2"]);
+    }
+
+    #[test]
+    fn search_phrase_needs_positions() {
+        let index = oracle_index();
+        assert!(matches!(
+            index.search_phrase("oracle database", 0, &SearchOptions::default()),
+            Err(Error::PositionsNotStored)
+        ));
+
+        let index: Index =
+            serde_json::from_value(serde_json::to_value(phrase_index()).unwrap()).unwrap();
+        let results = index
+            .search_phrase("error handling", 0, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(refs(&results), ["1", "3"]);
+    }
+
+    #[test]
+    fn search_empty_query() {
+        let index = oracle_index();
+        assert!(index.search("", &SearchOptions::default()).is_empty());
+        assert!(index.search("the", &SearchOptions::default()).is_empty());
+    }
+}
@@ -0,0 +1,364 @@
+//! Splits an [`Index`] into several smaller JSON files, so that search clients can download only
+//! the parts of a large index they need. Most users only need [`Index::to_shards`].
+//!
+//! The inverted indexes of every field are split by the first characters of their tokens into
+//! index shards, and the document store is split into ranges of document references. Each shard
+//! is the `index` or `documentStore` of a serialized elasticlunr.js index with only some of the
+//! tokens or documents, so merging shards gives back a part of the original index. A
+//! [`Manifest`] lists the shards along with the rest of the index settings.
+//!
+//! To search for a query token, a client fetches the index shard keyed by the token's first
+//! [`prefix_length`](Manifest::prefix_length) characters, or by the whole token if it is
+//! shorter, and the document shards of the documents it matches.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::document_store::DocumentStore;
+use crate::inverted_index::InvertedIndex;
+use crate::{is_false, Error, Index};
+
+/// The name of the manifest file written by [`ShardedIndex::write_to_dir`].
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Configures how [`Index::to_shards`] splits an index.
+///
+/// |Field           |Default|
+/// |----------------|-------|
+/// |`prefix_length` |`1`    |
+/// |`docs_per_shard`|`1000` |
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ShardOptions {
+    /// The number of characters of each token which choose its index shard. A `prefix_length`
+    /// of 0 puts every token in one shard.
+    pub prefix_length: usize,
+    /// The maximum number of documents in each document shard. A `docs_per_shard` of 0 puts
+    /// every document in one shard.
+    pub docs_per_shard: usize,
+}
+
+impl Default for ShardOptions {
+    fn default() -> Self {
+        ShardOptions {
+            prefix_length: 1,
+            docs_per_shard: 1000,
+        }
+    }
+}
+
+/// Describes a sharded index: the settings of the index, and which file holds each shard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: String,
+    pub fields: Vec<String>,
+    #[serde(rename = "ref")]
+    pub ref_field: String,
+    pub pipeline: Vec<String>,
+    pub lang: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub store_positions: bool,
+    /// Whether the document shards include the text of each document.
+    pub save: bool,
+    /// The number of documents in the index, which is needed to score search results.
+    pub document_count: usize,
+    /// The number of characters of each token which choose its index shard.
+    pub prefix_length: usize,
+    /// The file of each index shard, keyed by token prefix.
+    pub index_shards: BTreeMap<String, String>,
+    /// The document shards, in order of document reference.
+    pub document_shards: Vec<DocumentShard>,
+}
+
+/// The range of document references in a document shard, and the file which holds it.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct DocumentShard {
+    /// The first document reference in the shard.
+    pub first: String,
+    /// The last document reference in the shard.
+    pub last: String,
+    pub file: String,
+}
+
+/// An index split into shards by [`Index::to_shards`].
+#[derive(Debug)]
+pub struct ShardedIndex {
+    manifest: Manifest,
+    index_shards: Vec<BTreeMap<String, InvertedIndex>>,
+    document_shards: Vec<DocumentStore>,
+}
+
+impl ShardedIndex {
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Returns the name and JSON of every file of the sharded index, starting with the manifest.
+    pub fn to_json_files(&self) -> Vec<(String, String)> {
+        let mut files = vec![(
+            MANIFEST_FILE.to_string(),
+            serde_json::to_string(&self.manifest).unwrap(),
+        )];
+        for (file, shard) in self.manifest.index_shards.values().zip(&self.index_shards) {
+            files.push((file.clone(), serde_json::to_string(shard).unwrap()));
+        }
+        for (info, shard) in self
+            .manifest
+            .document_shards
+            .iter()
+            .zip(&self.document_shards)
+        {
+            files.push((info.file.clone(), serde_json::to_string(shard).unwrap()));
+        }
+        files
+    }
+
+    /// Writes the manifest and every shard to JSON files in `dir`, creating it if it doesn't
+    /// exist.
+    pub fn write_to_dir<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        write_json_file(&dir.join(MANIFEST_FILE), &self.manifest)?;
+        for (file, shard) in self.manifest.index_shards.values().zip(&self.index_shards) {
+            write_json_file(&dir.join(file), shard)?;
+        }
+        for (info, shard) in self
+            .manifest
+            .document_shards
+            .iter()
+            .zip(&self.document_shards)
+        {
+            write_json_file(&dir.join(&info.file), shard)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_json_file<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+impl Index {
+    /// Splits the index into shards, which can be written to separate files for search clients
+    /// to fetch as they need them. See the [`shard`](crate::shard) module for the format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use elasticlunr::{Index, shard::ShardOptions};
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["Apples", "Apples are a fruit"]);
+    /// index.add_doc("2", &["Bananas", "Bananas are also a fruit"]);
+    ///
+    /// let sharded = index.to_shards(&ShardOptions {
+    ///     docs_per_shard: 1,
+    ///     ..Default::default()
+    /// });
+    /// let manifest = sharded.manifest();
+    /// assert_eq!(
+    ///     manifest.index_shards.keys().collect::<Vec<_>>(),
+    ///     ["a", "b", "f"]
+    /// );
+    /// assert_eq!(manifest.document_shards.len(), 2);
+    /// # let dir = std::env::temp_dir().join("elasticlunr-shard-doctest");
+    /// sharded.write_to_dir(&dir).unwrap();
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn to_shards(&self, options: &ShardOptions) -> ShardedIndex {
+        let mut field_shards: BTreeMap<&str, BTreeMap<String, InvertedIndex>> = self
+            .index
+            .iter()
+            .map(|(field, index)| (field.as_str(), index.split_by_prefix(options.prefix_length)))
+            .collect();
+
+        let mut prefixes: Vec<String> = field_shards
+            .values()
+            .flat_map(|shards| shards.keys().cloned())
+            .collect();
+        prefixes.sort();
+        prefixes.dedup();
+
+        let mut index_files = BTreeMap::new();
+        let mut index_shards = Vec::with_capacity(prefixes.len());
+        for (i, prefix) in prefixes.into_iter().enumerate() {
+            let shard = field_shards
+                .iter_mut()
+                .map(|(field, shards)| {
+                    (
+                        field.to_string(),
+                        shards.remove(&prefix).unwrap_or_default(),
+                    )
+                })
+                .collect();
+            index_files.insert(prefix, format!("index-{}.json", i));
+            index_shards.push(shard);
+        }
+
+        let store = &self.document_store;
+        let refs: Vec<&String> = store.docs.keys().collect();
+        let chunk_size = match options.docs_per_shard {
+            0 => refs.len().max(1),
+            n => n,
+        };
+        let mut document_files = Vec::new();
+        let mut document_shards = Vec::new();
+        for (i, chunk) in refs.chunks(chunk_size).enumerate() {
+            let mut shard = DocumentStore::new(store.save);
+            for &doc_ref in chunk {
+                shard
+                    .docs
+                    .insert(doc_ref.clone(), store.docs[doc_ref].clone());
+                if let Some(info) = store.doc_info.get(doc_ref) {
+                    shard.doc_info.insert(doc_ref.clone(), info.clone());
+                }
+            }
+            shard.length = shard.docs.len();
+
+            document_files.push(DocumentShard {
+                first: chunk[0].clone(),
+                last: chunk[chunk.len() - 1].clone(),
+                file: format!("documents-{}.json", i),
+            });
+            document_shards.push(shard);
+        }
+
+        ShardedIndex {
+            manifest: Manifest {
+                version: self.version.to_string(),
+                fields: self.fields.clone(),
+                ref_field: self.ref_field.clone(),
+                pipeline: self.pipeline.names(),
+                lang: self.lang.name(),
+                store_positions: self.store_positions,
+                save: store.save,
+                document_count: store.len(),
+                prefix_length: options.prefix_length,
+                index_shards: index_files,
+                document_shards: document_files,
+            },
+            index_shards,
+            document_shards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_index() -> Index {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc("1", &["Apples", "Apples are a fruit"]);
+        index.add_doc("2", &["Bananas", "Bananas are also a fruit"]);
+        index.add_doc("3", &["Cherries", "Cherries are small"]);
+        index
+    }
+
+    #[test]
+    fn sharding_index() {
+        let index = sample_index();
+        let sharded = index.to_shards(&ShardOptions {
+            prefix_length: 1,
+            docs_per_shard: 2,
+        });
+
+        let manifest = sharded.manifest();
+        assert_eq!(manifest.fields, ["title", "body"]);
+        assert_eq!(manifest.pipeline, ["trimmer", "stopWordFilter", "stemmer"]);
+        assert_eq!(manifest.lang, "English");
+        assert_eq!(manifest.document_count, 3);
+        assert_eq!(
+            manifest.index_shards,
+            btreemap! {
+                "a".into() => "index-0.json".into(),
+                "b".into() => "index-1.json".into(),
+                "c".into() => "index-2.json".into(),
+                "f".into() => "index-3.json".into(),
+                "s".into() => "index-4.json".into(),
+            }
+        );
+        assert_eq!(
+            manifest.document_shards,
+            [
+                DocumentShard {
+                    first: "1".into(),
+                    last: "2".into(),
+                    file: "documents-0.json".into(),
+                },
+                DocumentShard {
+                    first: "3".into(),
+                    last: "3".into(),
+                    file: "documents-1.json".into(),
+                },
+            ]
+        );
+
+        let files = sharded.to_json_files();
+        assert_eq!(files.len(), 8);
+        assert_eq!(files[0].0, MANIFEST_FILE);
+        let manifest: Manifest = serde_json::from_str(&files[0].1).unwrap();
+        assert_eq!(&manifest, sharded.manifest());
+
+        // Merging the shards gives back the original index.
+        let mut merged_index: BTreeMap<String, InvertedIndex> = BTreeMap::new();
+        let mut merged_store = DocumentStore::new(true);
+        for (file, json) in &files[1..] {
+            if file.starts_with("index-") {
+                let shard: BTreeMap<String, InvertedIndex> = serde_json::from_str(json).unwrap();
+                for (field, index) in shard {
+                    merged_index.entry(field).or_default().merge(index);
+                }
+            } else {
+                merged_store.merge(serde_json::from_str(json).unwrap());
+            }
+        }
+        assert_eq!(
+            serde_json::to_value(&merged_index).unwrap(),
+            serde_json::to_value(&index.index).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&merged_store).unwrap(),
+            serde_json::to_value(&index.document_store).unwrap()
+        );
+    }
+
+    #[test]
+    fn sharding_into_single_shards() {
+        let index = sample_index();
+        let sharded = index.to_shards(&ShardOptions {
+            prefix_length: 0,
+            docs_per_shard: 0,
+        });
+        assert_eq!(
+            sharded.manifest().index_shards,
+            btreemap! { "".into() => "index-0.json".into() }
+        );
+        assert_eq!(sharded.manifest().document_shards.len(), 1);
+
+        let sharded = Index::new(&["title"]).to_shards(&Default::default());
+        assert!(sharded.manifest().index_shards.is_empty());
+        assert!(sharded.manifest().document_shards.is_empty());
+        assert_eq!(sharded.to_json_files().len(), 1);
+    }
+
+    #[test]
+    fn writing_shards_to_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("elasticlunr-writing-shards-{}", std::process::id()));
+        let sharded = sample_index().to_shards(&Default::default());
+        sharded.write_to_dir(&dir).unwrap();
+
+        for (file, json) in sharded.to_json_files() {
+            assert_eq!(fs::read_to_string(dir.join(file)).unwrap(), json);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
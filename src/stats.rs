@@ -0,0 +1,235 @@
+//! Statistics about the contents and the serialized size of an [`Index`], for finding out what
+//! makes an index large. Most users only need [`Index::stats`].
+
+use std::fmt;
+use std::io;
+
+use serde::Serialize;
+
+use crate::Index;
+
+/// Statistics about an index, returned by [`Index::stats`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    /// The number of documents in the index.
+    pub document_count: usize,
+    /// The statistics of each field, in the order of the fields of the index.
+    pub fields: Vec<FieldStats>,
+    /// The size of the JSON of the inverted indexes of every field, in bytes.
+    pub index_bytes: usize,
+    /// The size of the JSON of the document store, in bytes.
+    pub document_store_bytes: usize,
+    /// The size of the JSON of the whole index, in bytes.
+    pub total_bytes: usize,
+}
+
+/// Statistics about a field of an index.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldStats {
+    pub field: String,
+    /// The number of distinct tokens in the field.
+    pub vocabulary_size: usize,
+    /// The number of nodes in the trie of the inverted index of the field, which is serialized
+    /// as one JSON object per node.
+    pub node_count: usize,
+    /// The number of postings in the field, which is the sum of the document frequencies of its
+    /// tokens.
+    pub postings: usize,
+    /// The average number of tokens in the field, over the documents which have it.
+    pub average_length: f64,
+    /// The size of the JSON of the inverted index of the field, in bytes.
+    pub index_bytes: usize,
+    /// The tokens which are in the most documents, with their document frequencies. Tokens with
+    /// the same frequency are in lexical order.
+    pub top_terms: Vec<(String, i64)>,
+}
+
+impl fmt::Display for IndexStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Documents: {}", self.document_count)?;
+        writeln!(
+            f,
+            "Size: {} bytes ({} index, {} document store)",
+            self.total_bytes, self.index_bytes, self.document_store_bytes
+        )?;
+        for field in &self.fields {
+            writeln!(f, "Field {}:", field.field)?;
+            writeln!(f, "  Vocabulary: {} tokens", field.vocabulary_size)?;
+            writeln!(f, "  Trie nodes: {}", field.node_count)?;
+            writeln!(f, "  Postings: {}", field.postings)?;
+            writeln!(f, "  Average length: {:.2} tokens", field.average_length)?;
+            writeln!(f, "  Size: {} bytes", field.index_bytes)?;
+            if !field.top_terms.is_empty() {
+                let terms: Vec<_> = field
+                    .top_terms
+                    .iter()
+                    .map(|(term, doc_freq)| format!("{} ({})", term, doc_freq))
+                    .collect();
+                writeln!(f, "  Top terms: {}", terms.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Index {
+    /// Returns statistics about the contents of the index and the size of its JSON, including
+    /// the `top_terms` tokens of each field which are in the most documents.
+    ///
+    /// The sizes are measured by serializing the index without keeping the JSON in memory.
+    ///
+    /// # Example
+    /// ```
+    /// # use elasticlunr::Index;
+    /// let mut index = Index::new(&["title", "body"]);
+    /// index.add_doc("1", &["Apples", "Apples are a fruit"]);
+    /// index.add_doc("2", &["Bananas", "Bananas are also a fruit"]);
+    ///
+    /// let stats = index.stats(1);
+    /// assert_eq!(stats.document_count, 2);
+    /// assert_eq!(stats.fields[1].vocabulary_size, 3);
+    /// assert_eq!(stats.fields[1].top_terms, [("fruit".to_string(), 2)]);
+    /// assert_eq!(stats.total_bytes, index.to_json().len());
+    /// println!("{}", stats);
+    /// ```
+    pub fn stats(&self, top_terms: usize) -> IndexStats {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                let mut stats = FieldStats {
+                    field: field.clone(),
+                    vocabulary_size: 0,
+                    node_count: 0,
+                    postings: 0,
+                    average_length: 0.,
+                    index_bytes: 0,
+                    top_terms: Vec::new(),
+                };
+
+                if let Some(index) = self.index.get(field) {
+                    let mut terms: Vec<_> = index.terms().collect();
+                    stats.vocabulary_size = terms.len();
+                    stats.postings = terms.iter().map(|(_, doc_freq)| *doc_freq as usize).sum();
+                    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                    terms.truncate(top_terms);
+                    stats.top_terms = terms;
+                    stats.node_count = index.node_count();
+                    stats.index_bytes = json_bytes(index);
+                }
+
+                let lengths: Vec<usize> = self
+                    .document_store
+                    .doc_info
+                    .values()
+                    .filter_map(|info| info.get(field).copied())
+                    .collect();
+                if !lengths.is_empty() {
+                    stats.average_length =
+                        lengths.iter().sum::<usize>() as f64 / lengths.len() as f64;
+                }
+                stats
+            })
+            .collect();
+
+        IndexStats {
+            document_count: self.document_store.len(),
+            fields,
+            index_bytes: json_bytes(&self.index),
+            document_store_bytes: json_bytes(&self.document_store),
+            total_bytes: json_bytes(self),
+        }
+    }
+}
+
+/// Counts the bytes written to it, without storing them.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the length of the JSON of `value`.
+fn json_bytes<T: Serialize + ?Sized>(value: &T) -> usize {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).unwrap();
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_stats() {
+        let mut index = Index::new(&["title", "body"]);
+        index.add_doc("1", &["Apple pie", "An apple a day"]);
+        index.add_doc("2", &["Apple", "Apples and pears"]);
+        index.add_doc("3", &["Pear"]);
+
+        let stats = index.stats(2);
+        assert_eq!(stats.document_count, 3);
+
+        let title = &stats.fields[0];
+        assert_eq!(title.field, "title");
+        assert_eq!(title.vocabulary_size, 3);
+        // The root, a-p-p-l, p-i-e and e-a-r after the shared p.
+        assert_eq!(title.node_count, 11);
+        assert_eq!(title.postings, 4);
+        assert_eq!(title.average_length, 4. / 3.);
+        assert_eq!(
+            title.top_terms,
+            [("appl".to_string(), 2), ("pear".to_string(), 1)]
+        );
+
+        let body = &stats.fields[1];
+        assert_eq!(body.vocabulary_size, 3);
+        assert_eq!(body.postings, 4);
+        // "an", "a" and "and" are stop words.
+        assert_eq!(body.average_length, 2.);
+        assert_eq!(
+            body.top_terms,
+            [("appl".to_string(), 2), ("day".to_string(), 1)]
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&index.to_json()).unwrap();
+        assert_eq!(
+            title.index_bytes,
+            serde_json::to_string(&json["index"]["title"])
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            stats.index_bytes,
+            serde_json::to_string(&json["index"]).unwrap().len()
+        );
+        assert_eq!(
+            stats.document_store_bytes,
+            serde_json::to_string(&json["documentStore"]).unwrap().len()
+        );
+        assert_eq!(stats.total_bytes, index.to_json().len());
+
+        let report = stats.to_string();
+        assert!(report.starts_with("Documents: 3\n"));
+        assert!(report.contains("  Top terms: appl (2), pear (1)\n"));
+    }
+
+    #[test]
+    fn empty_index_stats() {
+        let stats = Index::new(&["title"]).stats(10);
+        assert_eq!(stats.document_count, 0);
+        assert_eq!(stats.fields[0].vocabulary_size, 0);
+        assert_eq!(stats.fields[0].node_count, 1);
+        assert_eq!(stats.fields[0].average_length, 0.);
+        assert!(stats.fields[0].top_terms.is_empty());
+    }
+}
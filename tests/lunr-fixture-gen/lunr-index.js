@@ -0,0 +1,23 @@
+// Generates ../data/lunr.out.json, an index of the documents of ../data/search.in.json built by
+// lunr.js, for comparing with the index exported by `Index::to_lunr_json`. It needs lunr.js 2.3.9,
+// the version the exported indexes are written for.
+var lunr = require('lunr');
+const fs = require('fs');
+
+if (lunr.version !== '2.3.9') {
+    throw new Error('lunr.js 2.3.9 is required, found ' + lunr.version);
+}
+
+const fixture = JSON.parse(fs.readFileSync('../data/search.in.json'));
+
+var index = lunr(function () {
+    this.ref('id');
+    for (let field of fixture.fields) {
+        this.field(field);
+    }
+    for (let doc of fixture.docs) {
+        this.add(doc);
+    }
+});
+
+fs.writeFileSync('../data/lunr.out.json', JSON.stringify(index, null, 2) + '\n');
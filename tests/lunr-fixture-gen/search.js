@@ -0,0 +1,26 @@
+// Generates ../data/search.out.json, the results of searching an index with elasticlunr.js, for
+// comparing with the results of `Index::search`.
+var elasticlunr = require('elasticlunr');
+const fs = require('fs');
+
+const fixture = JSON.parse(fs.readFileSync('../data/search.in.json'));
+
+var index = elasticlunr(function () {
+    this.setRef('id');
+    for (let field of fixture.fields) {
+        this.addField(field);
+    }
+});
+for (let doc of fixture.docs) {
+    index.addDoc(doc);
+}
+
+for (let search of fixture.searches) {
+    search.results = index.search(search.query, search.options)
+        .map(result => [result.ref, result.score]);
+    // Write `bool` and `expand`, since `SearchOptions` has no defaults for them when deserialized.
+    // `fields` is left out when absent, which searches every field.
+    search.options = Object.assign({bool: 'OR', expand: false}, search.options);
+}
+
+fs.writeFileSync('../data/search.out.json', JSON.stringify(fixture, null, 2) + '\n');
@@ -0,0 +1,85 @@
+#![cfg(feature = "derive")]
+
+use elasticlunr::config::SearchOptions;
+use elasticlunr::{Document, Index, IndexBuilder};
+
+fn split_commas(text: &str) -> Vec<String> {
+    text.split(',').map(|s| s.trim().to_lowercase()).collect()
+}
+
+#[derive(Document)]
+struct Page {
+    title: String,
+    #[elasticlunr(ref)]
+    url: String,
+    body: Option<String>,
+    #[elasticlunr(tokenizer = "split_commas")]
+    tags: String,
+    #[elasticlunr(store_only)]
+    updated: u32,
+    #[elasticlunr(skip)]
+    #[allow(dead_code)]
+    views: u64,
+}
+
+#[derive(Document)]
+struct Post<'a> {
+    id: u32,
+    r#type: &'a str,
+    keywords: Vec<&'a str>,
+}
+
+#[test]
+fn derived_fields() {
+    let index = IndexBuilder::new().add_document_fields::<Page>().build();
+    assert_eq!(index.get_fields(), &["title", "body", "tags"]);
+
+    let index = IndexBuilder::new().add_document_fields::<Post>().build();
+    assert_eq!(index.get_fields(), &["type", "keywords"]);
+}
+
+#[test]
+fn add_derived_document() {
+    let mut index = IndexBuilder::new().add_document_fields::<Page>().build();
+    index.add_document(&Page {
+        title: "Getting Started".into(),
+        url: "/start.html".into(),
+        body: None,
+        tags: "New York, guide".into(),
+        updated: 20240101,
+        views: 7,
+    });
+
+    let mut expected = IndexBuilder::new()
+        .set_ref("url")
+        .add_field("title")
+        .add_field("body")
+        .add_field_with_tokenizer("tags", Box::new(split_commas))
+        .build();
+    expected.add_doc("/start.html", &["Getting Started", "", "New York, guide"]);
+
+    let mut json: serde_json::Value = serde_json::from_str(&expected.to_json()).unwrap();
+    json["documentStore"]["docs"]["/start.html"]["updated"] = "20240101".into();
+    assert_eq!(
+        json,
+        serde_json::from_str::<serde_json::Value>(&index.to_json()).unwrap()
+    );
+
+    let results = index.search("New York", &SearchOptions::default());
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].doc_ref, "/start.html");
+}
+
+#[test]
+fn add_derived_document_with_default_ref() {
+    let mut index = IndexBuilder::new().add_document_fields::<Post>().build();
+    index.add_document(&Post {
+        id: 1,
+        r#type: "article",
+        keywords: vec!["apple", "banana"],
+    });
+
+    let mut expected = Index::new(&["type", "keywords"]);
+    expected.add_doc("1", &["article", "apple banana"]);
+    assert_eq!(index.to_json(), expected.to_json());
+}
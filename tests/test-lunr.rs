@@ -0,0 +1,116 @@
+use elasticlunr::*;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// `lunr.out.json` is built by lunr.js 2.3.9 from the documents of `search.in.json` with
+// `lunr-fixture-gen/lunr-index.js`.
+fn read_json(name: &str) -> Value {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name);
+    let json = fs::read_to_string(src).unwrap();
+    serde_json::from_str(&json).expect("Unable to deserialize the fixture")
+}
+
+/// The postings of each term, without the term indexes, which lunr.js assigns in the order the
+/// terms are first seen.
+fn postings(index: &Value) -> BTreeMap<String, Value> {
+    index["invertedIndex"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            let mut posting = entry[1].as_object().unwrap().clone();
+            posting.remove("_index");
+            (
+                entry[0].as_str().unwrap().to_string(),
+                Value::Object(posting),
+            )
+        })
+        .collect()
+}
+
+/// The score of each term in each field vector, by term rather than by term index.
+fn field_vectors(index: &Value) -> BTreeMap<String, BTreeMap<String, f64>> {
+    let terms: BTreeMap<u64, &str> = index["invertedIndex"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry[1]["_index"].as_u64().unwrap(),
+                entry[0].as_str().unwrap(),
+            )
+        })
+        .collect();
+    index["fieldVectors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            let vector = entry[1].as_array().unwrap();
+            let scores = vector
+                .chunks(2)
+                .map(|pair| {
+                    let term = terms[&pair[0].as_u64().unwrap()].to_string();
+                    (term, pair[1].as_f64().unwrap())
+                })
+                .collect();
+            (entry[0].as_str().unwrap().to_string(), scores)
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "needs tests/data/lunr.out.json, generated with `npm run lunr` in tests/lunr-fixture-gen"]
+fn lunr_export_matches_lunr_js() {
+    let fixture = read_json("search.in.json");
+    let fields: Vec<&str> = fixture["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field.as_str().unwrap())
+        .collect();
+    let mut index = Index::new(&fields);
+    for doc in fixture["docs"].as_array().unwrap() {
+        let doc: &Map<String, Value> = doc.as_object().unwrap();
+        index.add_doc_serde(doc).unwrap();
+    }
+
+    let exported: Value = serde_json::from_str(&index.to_lunr_json()).unwrap();
+    let expected = read_json("lunr.out.json");
+
+    for key in &["version", "fields", "pipeline"] {
+        assert_eq!(exported[key], expected[key], "{}", key);
+    }
+    assert_eq!(postings(&exported), postings(&expected));
+
+    let exported = field_vectors(&exported);
+    let expected = field_vectors(&expected);
+    assert_eq!(
+        exported.keys().collect::<Vec<_>>(),
+        expected.keys().collect::<Vec<_>>()
+    );
+    for (field_ref, scores) in &exported {
+        let expected_scores = &expected[field_ref];
+        assert_eq!(
+            scores.keys().collect::<Vec<_>>(),
+            expected_scores.keys().collect::<Vec<_>>(),
+            "{}",
+            field_ref
+        );
+        for (term, score) in scores {
+            // Both sides round the scores to three decimals.
+            assert!(
+                (score - expected_scores[term]).abs() < 1e-9,
+                "{} {}: found {}, expected {}",
+                field_ref,
+                term,
+                score,
+                expected_scores[term]
+            );
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use elasticlunr::config::SearchOptions;
+use elasticlunr::*;
+use serde_derive::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// The results in `search.out.json` were generated by elasticlunr.js with `lunr-fixture-gen/search.js`.
+#[derive(Deserialize)]
+struct Fixture {
+    fields: Vec<String>,
+    docs: Vec<serde_json::Map<String, serde_json::Value>>,
+    searches: Vec<Search>,
+}
+
+#[derive(Deserialize)]
+struct Search {
+    query: String,
+    options: SearchOptions,
+    results: Vec<(String, f64)>,
+}
+
+fn read_fixture() -> Fixture {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/search.out.json");
+    let json = fs::read_to_string(src).unwrap();
+    serde_json::from_str(&json).expect("Unable to deserialize the fixture")
+}
+
+#[test]
+fn search_results_match_elasticlunr_js() {
+    let fixture = read_fixture();
+    let mut index = Index::new(&fixture.fields);
+    for doc in &fixture.docs {
+        index.add_doc_serde(doc).unwrap();
+    }
+
+    for search in &fixture.searches {
+        let results = index.search(&search.query, &search.options);
+        let results: Vec<_> = results
+            .into_iter()
+            .map(|result| (result.doc_ref, result.score))
+            .collect();
+
+        let matches = results.len() == search.results.len()
+            && results
+                .iter()
+                .zip(&search.results)
+                .all(|(a, b)| a.0 == b.0 && (a.1 - b.1).abs() < 1e-12);
+        assert!(
+            matches,
+            "{:?} with {:?}:\n  found {:?}\n  expected {:?}",
+            search.query, search.options, results, search.results
+        );
+    }
+}